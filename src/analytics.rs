@@ -0,0 +1,413 @@
+use crate::breakdown::Breakdowns;
+use crate::distribution::ServiceDistribution;
+use crate::duration::format_duration;
+use crate::replication::t_quantile_95;
+use crate::window::Window;
+use serde::Deserialize;
+
+/// Steady-state queueing model of a single-stage drive-through: Poisson arrivals, a
+/// general service time distribution and `servers` identical windows sharing one queue
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueingModel {
+    /// Customers per second
+    pub arrival_rate: f64,
+    pub mean_service: f64,
+    /// Squared coefficient of variation of the service time (variance / mean²)
+    pub service_scv: f64,
+    pub servers: usize,
+    /// Kendall notation of the service time distribution (`M`, `D` or `G`)
+    service_kind: char,
+}
+
+/// Steady-state performance predicted by a [`QueueingModel`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SteadyState {
+    pub utilization: f64,
+    /// Probability that an arriving customer has to wait (Erlang C): exact for exponential
+    /// service or a single window, an approximation for other service times with several
+    /// windows
+    pub wait_probability: f64,
+    pub avg_queue_length: f64,
+    pub avg_wait: f64,
+    pub avg_system_time: f64,
+}
+
+impl QueueingModel {
+    /// Creates the model for arrivals every `avg_arrival_interval` on average and service
+    /// times following `service`
+    #[must_use]
+    pub fn new(avg_arrival_interval: f64, service: &ServiceDistribution, servers: usize) -> Self {
+        let mean_service = service.mean();
+        let service_kind = match service {
+            ServiceDistribution::Exponential { .. } => 'M',
+            ServiceDistribution::Deterministic { .. } => 'D',
+            _ => 'G',
+        };
+        Self {
+            arrival_rate: 1.0 / avg_arrival_interval,
+            mean_service,
+            service_scv: service.variance() / (mean_service * mean_service),
+            servers,
+            service_kind,
+        }
+    }
+
+    /// Returns the name of the model and of the formula used, e.g. `M/M/2, Erlang C`
+    pub fn name(&self) -> String {
+        let formula = match (self.service_kind, self.servers) {
+            ('M', _) => "Erlang C",
+            (_, 1) => "Pollaczek-Khinchine",
+            ('D', _) => "Cosmetatos approximation",
+            _ => "Allen-Cunneen approximation",
+        };
+        format!("M/{}/{}, {}", self.service_kind, self.servers, formula)
+    }
+
+    /// Returns the offered load per window, λ·E[S]/c
+    pub fn utilization(&self) -> f64 {
+        self.arrival_rate * self.mean_service / self.servers as f64
+    }
+
+    /// Returns the steady-state performance, or None if the windows cannot keep up (ρ ≥ 1)
+    ///
+    /// The waiting time is exact for M/M/c (Erlang C) and M/G/1 (Pollaczek-Khinchine). With
+    /// several windows, deterministic service (M/D/c) halves the M/M/c wait and corrects it
+    /// by 1 + (1 - ρ)(c - 1)(√(4 + 5c) - 2) / (16ρc) (Cosmetatos); other service times
+    /// scale it by (1 + c_s²)/2 (Allen-Cunneen).
+    pub fn steady_state(&self) -> Option<SteadyState> {
+        let utilization = self.utilization();
+        if utilization >= 1.0 {
+            return None;
+        }
+        let c = self.servers as f64;
+        let wait_probability = erlang_c(self.servers, self.arrival_rate * self.mean_service);
+        let mmc_wait = wait_probability * self.mean_service / (c * (1.0 - utilization));
+        let avg_wait = match self.service_kind {
+            'D' if self.servers > 1 => {
+                let correction = (1.0 - utilization) * (c - 1.0) * ((4.0 + 5.0 * c).sqrt() - 2.0)
+                    / (16.0 * utilization * c);
+                mmc_wait / 2.0 * (1.0 + correction)
+            }
+            _ => mmc_wait * (1.0 + self.service_scv) / 2.0,
+        };
+        Some(SteadyState {
+            utilization,
+            wait_probability,
+            avg_queue_length: self.arrival_rate * avg_wait,
+            avg_wait,
+            avg_system_time: avg_wait + self.mean_service,
+        })
+    }
+}
+
+/// Returns the probability of waiting in an M/M/c queue with `servers` windows and an
+/// offered load of `load` Erlangs (Erlang C formula)
+///
+/// Computed from the Erlang B recursion, which stays accurate for many windows.
+pub fn erlang_c(servers: usize, load: f64) -> f64 {
+    let mut blocking = 1.0;
+    for k in 1..=servers {
+        blocking = load * blocking / (k as f64 + load * blocking);
+    }
+    let utilization = load / servers as f64;
+    blocking / (1.0 - utilization * (1.0 - blocking))
+}
+
+/// Number of batches at which [`BatchMeans`] merges neighbouring batches
+const MAX_BATCHES: usize = 40;
+
+/// Confidence interval of the mean of a long, autocorrelated series with the batch means
+/// method, in constant memory
+///
+/// Consecutive values are averaged in batches; whenever there are [`MAX_BATCHES`]
+/// batches, neighbouring batches are merged and the batch size doubles, so between 20 and
+/// 39 batches of ever larger size summarize the series.
+#[derive(Debug, Clone)]
+pub struct BatchMeans {
+    batch_size: usize,
+    /// Sums of the complete batches
+    sums: Vec<f64>,
+    current_sum: f64,
+    current_count: usize,
+}
+
+impl BatchMeans {
+    /// Creates an empty series
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            batch_size: 1,
+            sums: Vec::new(),
+            current_sum: 0.0,
+            current_count: 0,
+        }
+    }
+
+    /// Records the next value of the series
+    pub fn record(&mut self, value: f64) {
+        self.current_sum += value;
+        self.current_count += 1;
+        if self.current_count < self.batch_size {
+            return;
+        }
+        self.sums.push(self.current_sum);
+        self.current_sum = 0.0;
+        self.current_count = 0;
+        if self.sums.len() == MAX_BATCHES {
+            self.sums = self
+                .sums
+                .chunks_exact(2)
+                .map(|pair| pair[0] + pair[1])
+                .collect();
+            self.batch_size *= 2;
+        }
+    }
+
+    /// Returns the mean of the complete batches and the half-width of its 95% confidence
+    /// interval, or None until there are enough batches (at least `MAX_BATCHES / 2`)
+    pub fn confidence_interval(&self) -> Option<(f64, f64)> {
+        let n = self.sums.len();
+        if n < MAX_BATCHES / 2 {
+            return None;
+        }
+        let means: Vec<f64> = self
+            .sums
+            .iter()
+            .map(|s| s / self.batch_size as f64)
+            .collect();
+        let mean = means.iter().sum::<f64>() / n as f64;
+        let variance = means.iter().map(|m| (m - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        Some((mean, t_quantile_95(n - 1) * (variance / n as f64).sqrt()))
+    }
+}
+
+impl Default for BatchMeans {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What to do when the offered load reaches the capacity of the windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverloadPolicy {
+    /// Print a warning and run anyway
+    #[default]
+    Warn,
+    /// Refuse to run: loading the configuration fails
+    Refuse,
+}
+
+/// Work arriving in a period (or at a station) compared with the service capacity
+#[derive(Debug, Clone, PartialEq)]
+pub struct OfferedLoad {
+    /// Period or station the load applies to, e.g. `11h 30m - 13h 30m` or `pay`
+    pub label: String,
+    /// Customers per second (the peak rate of the period)
+    pub arrival_rate: f64,
+    pub mean_service: f64,
+    /// Windows open, counted as normal-speed windows that never break down
+    pub capacity: f64,
+}
+
+impl OfferedLoad {
+    /// Returns the expected utilization ρ = λ·E[S]/c (infinite when no window is open)
+    pub fn utilization(&self) -> f64 {
+        if self.capacity > 0.0 {
+            self.arrival_rate * self.mean_service / self.capacity
+        } else if self.arrival_rate > 0.0 {
+            f64::INFINITY
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns true if the queue grows without bound at this load (ρ ≥ 1)
+    pub fn is_overloaded(&self) -> bool {
+        self.utilization() >= 1.0
+    }
+
+    /// Describes the overload for warnings and errors
+    pub fn overload_message(&self) -> String {
+        let load = if self.capacity > 0.0 {
+            format!("offered load of {:.2}%", self.utilization() * 100.0)
+        } else {
+            "arrivals with no window open".to_string()
+        };
+        format!(
+            "{} ({}): the windows cannot keep up and the queue grows without bound",
+            load, self.label
+        )
+    }
+}
+
+/// Returns the capacity of each of `num_windows` windows in normal-speed windows: its speed
+/// times its availability under breakdowns
+pub fn window_capacities(
+    num_windows: usize,
+    windows: &[Window],
+    breakdowns: Option<&Breakdowns>,
+) -> Vec<f64> {
+    (0..num_windows)
+        .map(|i| {
+            let window = windows.get(i);
+            let speed = window.map_or(1.0, |w| w.speed);
+            let breakdowns = window.and_then(|w| w.breakdowns.as_ref()).or(breakdowns);
+            speed * breakdowns.map_or(1.0, Breakdowns::availability)
+        })
+        .collect()
+}
+
+/// Fails with the message of the first overloaded period if `policy` refuses overloads
+pub fn check_overload(loads: &[OfferedLoad], policy: OverloadPolicy) -> Result<(), String> {
+    match loads.iter().find(|l| l.is_overloaded()) {
+        Some(load) if policy == OverloadPolicy::Refuse => Err(format!(
+            "{} (set overload: warn to run anyway)",
+            load.overload_message()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Prints the expected utilization and a warning for every overloaded period
+pub fn print_offered_load(loads: &[OfferedLoad]) {
+    let Some(peak) = loads
+        .iter()
+        .max_by(|a, b| a.utilization().total_cmp(&b.utilization()))
+    else {
+        return;
+    };
+    let lowest = loads
+        .iter()
+        .map(OfferedLoad::utilization)
+        .fold(f64::INFINITY, f64::min);
+    if loads.len() == 1 {
+        println!(
+            "Expected utilization: {:.2}% (λ·E[S]/c = {:.4}/s × {} / {:.2} windows)",
+            peak.utilization() * 100.0,
+            peak.arrival_rate,
+            format_duration(peak.mean_service),
+            peak.capacity
+        );
+    } else if peak.utilization().is_finite() {
+        println!(
+            "Expected utilization: {:.2}% to {:.2}% (peak {})",
+            lowest * 100.0,
+            peak.utilization() * 100.0,
+            peak.label
+        );
+    }
+    for load in loads.iter().filter(|l| l.is_overloaded()) {
+        eprintln!("Warning: {}", load.overload_message());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_erlang_c() {
+        // One window: the probability of waiting is the utilization
+        assert!((erlang_c(1, 0.75) - 0.75).abs() < 1e-12);
+        // Two windows at 1.5 Erlangs: C = 0.6429
+        assert!((erlang_c(2, 1.5) - 0.642_857).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mm1_and_md1() {
+        let mm1 = QueueingModel::new(80.0, &ServiceDistribution::Exponential { mean: 60.0 }, 1);
+        let state = mm1.steady_state().unwrap();
+        assert_eq!(mm1.name(), "M/M/1, Erlang C");
+        assert!((state.utilization - 0.75).abs() < 1e-12);
+        // Wq = ρ/(μ - λ) = 0.75 / (1/60 - 1/80) = 180 s
+        assert!((state.avg_wait - 180.0).abs() < 1e-9);
+        assert!((state.avg_queue_length - 2.25).abs() < 1e-9);
+        assert!((state.avg_system_time - 240.0).abs() < 1e-9);
+
+        // Pollaczek-Khinchine: deterministic service halves the wait
+        let md1 = QueueingModel::new(80.0, &ServiceDistribution::Deterministic { value: 60.0 }, 1);
+        assert_eq!(md1.name(), "M/D/1, Pollaczek-Khinchine");
+        assert!((md1.steady_state().unwrap().avg_wait - 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mmc() {
+        let model = QueueingModel::new(40.0, &ServiceDistribution::Exponential { mean: 60.0 }, 2);
+        let state = model.steady_state().unwrap();
+        // λ = 1/40, μ = 1/60, c = 2: Wq = C / (cμ - λ) = 0.6429 * 120 = 77.14 s
+        assert!((state.avg_wait - 77.142_857).abs() < 1e-5);
+        assert_eq!(
+            QueueingModel::new(
+                40.0,
+                &ServiceDistribution::Uniform {
+                    min: 30.0,
+                    max: 90.0
+                },
+                2
+            )
+            .name(),
+            "M/G/2, Allen-Cunneen approximation"
+        );
+        assert!(
+            QueueingModel::new(20.0, &ServiceDistribution::Exponential { mean: 60.0 }, 2)
+                .steady_state()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_mdc_cosmetatos() {
+        let model =
+            QueueingModel::new(40.0, &ServiceDistribution::Deterministic { value: 60.0 }, 2);
+        assert_eq!(model.name(), "M/D/2, Cosmetatos approximation");
+        // Half the M/M/2 wait (77.14 s), corrected by 1 + 0.25 × 1 × (√14 - 2) / 24 = 1.0182
+        let expected = 77.142_857 / 2.0 * (1.0 + 0.25 * (14f64.sqrt() - 2.0) / 24.0);
+        assert!((model.steady_state().unwrap().avg_wait - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_offered_load() {
+        let load = OfferedLoad {
+            label: "lunch".to_string(),
+            arrival_rate: 1.0 / 20.0,
+            mean_service: 60.0,
+            capacity: 2.0,
+        };
+        assert!((load.utilization() - 1.5).abs() < 1e-12);
+        assert!(load.is_overloaded());
+        assert!(check_overload(std::slice::from_ref(&load), OverloadPolicy::Warn).is_ok());
+        let err = check_overload(&[load], OverloadPolicy::Refuse).unwrap_err();
+        assert!(err.contains("offered load of 150.00% (lunch)"), "{}", err);
+
+        let mut slow = Window::new("trainee");
+        slow.speed = 0.5;
+        let breakdowns = Breakdowns {
+            time_between_failures: ServiceDistribution::Deterministic { value: 900.0 },
+            repair_time: ServiceDistribution::Deterministic { value: 100.0 },
+            interruption: Default::default(),
+        };
+        assert_eq!(window_capacities(2, &[], None), vec![1.0, 1.0]);
+        assert_eq!(
+            window_capacities(2, &[Window::new("main"), slow], Some(&breakdowns)),
+            vec![0.9, 0.45]
+        );
+    }
+
+    #[test]
+    fn test_batch_means() {
+        let mut batches = BatchMeans::new();
+        for i in 0..10 {
+            batches.record(i as f64);
+        }
+        assert_eq!(batches.confidence_interval(), None);
+
+        for i in 10..1000 {
+            batches.record((i % 10) as f64);
+        }
+        let (mean, half_width) = batches.confidence_interval().unwrap();
+        assert!((mean - 4.5).abs() < 0.1);
+        assert!(half_width > 0.0 && half_width < 1.0);
+        assert!(batches.sums.len() >= MAX_BATCHES / 2 && batches.sums.len() < MAX_BATCHES);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::duration::{deserialize_duration, deserialize_optional_duration, format_duration};
+use rand::Rng;
+use serde::Deserialize;
+
+/// A period of the day with its own arrival rate
+///
+/// The rate is given as an average inter-arrival `interval`. If `end_interval` is set,
+/// the arrival rate changes linearly from `1 / interval` at `from` to `1 / end_interval`
+/// at `to`; otherwise it is constant over the whole segment.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ArrivalSegment {
+    /// Optional label used in the report (defaults to the time range)
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub from: f64,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub to: f64,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: f64,
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub end_interval: Option<f64>,
+}
+
+impl ArrivalSegment {
+    /// Returns the label of the segment for reports
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!(
+                "{} - {}",
+                format_duration(self.from),
+                format_duration(self.to)
+            ),
+        }
+    }
+
+    /// Returns true if `time` falls within `[from, to)`
+    pub fn contains(&self, time: f64) -> bool {
+        time >= self.from && time < self.to
+    }
+
+    /// Returns the arrival rate (customers per second) at `time` within the segment
+    fn rate_at(&self, time: f64) -> f64 {
+        let start_rate = 1.0 / self.interval;
+        match self.end_interval {
+            Some(end_interval) => {
+                let end_rate = 1.0 / end_interval;
+                let fraction = (time - self.from) / (self.to - self.from);
+                start_rate + (end_rate - start_rate) * fraction
+            }
+            None => start_rate,
+        }
+    }
+
+    /// Returns the highest arrival rate reached within the segment
+    fn max_rate(&self) -> f64 {
+        let start_rate = 1.0 / self.interval;
+        match self.end_interval {
+            Some(end_interval) => start_rate.max(1.0 / end_interval),
+            None => start_rate,
+        }
+    }
+}
+
+/// Time-varying arrival rate for a non-homogeneous Poisson arrival process
+///
+/// Outside of the configured segments, customers arrive with the base average interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrivalProfile {
+    base_interval: f64,
+    segments: Vec<ArrivalSegment>,
+}
+
+impl ArrivalProfile {
+    /// Creates a profile with a constant average inter-arrival interval (homogeneous Poisson process)
+    #[must_use]
+    pub fn constant(avg_arrival_interval: f64) -> Self {
+        Self {
+            base_interval: avg_arrival_interval,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Creates a profile with the given base interval and rate segments
+    ///
+    /// Segments are sorted by start time.
+    pub fn new(base_interval: f64, mut segments: Vec<ArrivalSegment>) -> Result<Self, String> {
+        segments.sort_by(|a, b| a.from.total_cmp(&b.from));
+        let profile = Self {
+            base_interval,
+            segments,
+        };
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Checks that all intervals are positive and that segments do not overlap
+    pub fn validate(&self) -> Result<(), String> {
+        if self.base_interval <= 0.0 {
+            return Err("average arrival interval must be positive".into());
+        }
+        for segment in &self.segments {
+            let label = segment.label();
+            if segment.from < 0.0 || segment.to <= segment.from {
+                return Err(format!(
+                    "arrival segment '{}': expected 0 <= from < to",
+                    label
+                ));
+            }
+            if segment.interval <= 0.0 || segment.end_interval.is_some_and(|i| i <= 0.0) {
+                return Err(format!(
+                    "arrival segment '{}': intervals must be positive",
+                    label
+                ));
+            }
+        }
+        for pair in self.segments.windows(2) {
+            if pair[1].from < pair[0].to {
+                return Err(format!(
+                    "arrival segments '{}' and '{}' overlap",
+                    pair[0].label(),
+                    pair[1].label()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the rate segments, sorted by start time
+    pub fn segments(&self) -> &[ArrivalSegment] {
+        &self.segments
+    }
+
+    /// Returns the arrival rate (customers per second) at `time`
+    pub fn rate_at(&self, time: f64) -> f64 {
+        self.segments
+            .iter()
+            .find(|s| s.contains(time))
+            .map_or(1.0 / self.base_interval, |s| s.rate_at(time))
+    }
+
+    /// Returns the highest arrival rate in `[from, to)`, a period without a segment boundary
+    /// inside it
+    pub fn peak_rate(&self, from: f64, to: f64) -> f64 {
+        match self.segments.iter().find(|s| s.contains(from)) {
+            Some(segment) => segment.rate_at(from).max(segment.rate_at(to)),
+            None => 1.0 / self.base_interval,
+        }
+    }
+
+    /// Returns an upper bound of the arrival rate over the whole profile
+    fn max_rate(&self) -> f64 {
+        self.segments
+            .iter()
+            .map(ArrivalSegment::max_rate)
+            .fold(1.0 / self.base_interval, f64::max)
+    }
+
+    /// Generates arrival times in `(0, max_time]` by thinning
+    ///
+    /// Candidate arrivals are drawn from a homogeneous Poisson process at the maximum rate
+    /// and each is accepted with probability `rate_at(t) / max_rate`.
+    pub fn generate_arrivals<R: Rng + ?Sized>(&self, rng: &mut R, max_time: f64) -> Vec<f64> {
+        let max_rate = self.max_rate();
+        let mut arrivals = Vec::new();
+        let mut current = 0.0;
+
+        loop {
+            let u: f64 = 1.0 - rng.random::<f64>();
+            current += -u.ln() / max_rate;
+
+            if current > max_time {
+                break;
+            }
+
+            if self.segments.is_empty() || rng.random::<f64>() * max_rate < self.rate_at(current) {
+                arrivals.push(current);
+            }
+        }
+        arrivals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn segment(from: f64, to: f64, interval: f64, end_interval: Option<f64>) -> ArrivalSegment {
+        ArrivalSegment {
+            name: None,
+            from,
+            to,
+            interval,
+            end_interval,
+        }
+    }
+
+    #[test]
+    fn test_rate_at() {
+        let profile = ArrivalProfile::new(
+            60.0,
+            vec![
+                segment(100.0, 200.0, 15.0, None),
+                segment(300.0, 400.0, 10.0, Some(20.0)),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(profile.rate_at(50.0), 1.0 / 60.0);
+        assert_eq!(profile.rate_at(150.0), 1.0 / 15.0);
+        assert_eq!(profile.rate_at(200.0), 1.0 / 60.0);
+        assert!((profile.rate_at(350.0) - 0.075).abs() < 1e-12);
+        assert_eq!(profile.peak_rate(300.0, 400.0), 0.1);
+        assert_eq!(profile.peak_rate(200.0, 300.0), 1.0 / 60.0);
+    }
+
+    #[test]
+    fn test_overlapping_segments_rejected() {
+        let result = ArrivalProfile::new(
+            60.0,
+            vec![
+                segment(0.0, 200.0, 15.0, None),
+                segment(100.0, 300.0, 15.0, None),
+            ],
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_thinning_matches_segment_rates() {
+        // 10 hours at 60s base interval with a 2 hour rush at 15s
+        let profile =
+            ArrivalProfile::new(60.0, vec![segment(3600.0, 10800.0, 15.0, None)]).unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let mut rush = 0;
+        let mut quiet = 0;
+        for _ in 0..20 {
+            for t in profile.generate_arrivals(&mut rng, 36000.0) {
+                if (3600.0..10800.0).contains(&t) {
+                    rush += 1;
+                } else {
+                    quiet += 1;
+                }
+            }
+        }
+
+        // Expected per run: 7200/15 = 480 in the rush, 28800/60 = 480 outside
+        let rush_avg = rush as f64 / 20.0;
+        let quiet_avg = quiet as f64 / 20.0;
+        assert!(
+            (rush_avg - 480.0).abs() / 480.0 < 0.05,
+            "rush: {}",
+            rush_avg
+        );
+        assert!(
+            (quiet_avg - 480.0).abs() / 480.0 < 0.05,
+            "quiet: {}",
+            quiet_avg
+        );
+    }
+}
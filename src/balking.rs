@@ -0,0 +1,144 @@
+use rand::Rng;
+use serde::Deserialize;
+
+/// Rule deciding whether an arriving customer drives past instead of joining the lane
+///
+/// Rules look at the number of customers waiting in the queue (not those being served)
+/// at the moment of arrival. Selected in YAML with a `type` tag,
+/// e.g. `{ type: threshold, max_queue: 8 }`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BalkingRule {
+    /// Every customer balks once `max_queue` or more customers are waiting
+    Threshold { max_queue: usize },
+    /// Balking probability grows linearly from 0 at `min_queue` waiting customers to 1 at `max_queue`
+    Linear { min_queue: usize, max_queue: usize },
+    /// `probabilities[n]` is the balking probability with `n` customers waiting;
+    /// the last entry applies to all longer queues
+    Table { probabilities: Vec<f64> },
+}
+
+impl BalkingRule {
+    /// Checks that the rule parameters are consistent
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Threshold { .. } => {}
+            Self::Linear {
+                min_queue,
+                max_queue,
+            } => {
+                if max_queue <= min_queue {
+                    return Err("balking: max_queue must be greater than min_queue".into());
+                }
+            }
+            Self::Table { probabilities } => {
+                if probabilities.is_empty() {
+                    return Err("balking: at least one probability is required".into());
+                }
+                if probabilities.iter().any(|p| !(0.0..=1.0).contains(p)) {
+                    return Err("balking: probabilities must be between 0 and 1".into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the probability that a customer balks when `queue_len` customers are waiting
+    pub fn probability(&self, queue_len: usize) -> f64 {
+        match self {
+            Self::Threshold { max_queue } => {
+                if queue_len >= *max_queue {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Linear {
+                min_queue,
+                max_queue,
+            } => {
+                if queue_len <= *min_queue {
+                    0.0
+                } else if queue_len >= *max_queue {
+                    1.0
+                } else {
+                    (queue_len - min_queue) as f64 / (max_queue - min_queue) as f64
+                }
+            }
+            Self::Table { probabilities } => probabilities
+                .get(queue_len)
+                .or(probabilities.last())
+                .copied()
+                .unwrap_or(0.0),
+        }
+    }
+
+    /// Decides whether a customer arriving to `queue_len` waiting customers balks
+    pub fn should_balk<R: Rng + ?Sized>(&self, rng: &mut R, queue_len: usize) -> bool {
+        let p = self.probability(queue_len);
+        p >= 1.0 || (p > 0.0 && rng.random::<f64>() < p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_probability() {
+        let rule = BalkingRule::Threshold { max_queue: 3 };
+        assert_eq!(rule.probability(2), 0.0);
+        assert_eq!(rule.probability(3), 1.0);
+        assert_eq!(rule.probability(10), 1.0);
+    }
+
+    #[test]
+    fn test_linear_probability() {
+        let rule = BalkingRule::Linear {
+            min_queue: 2,
+            max_queue: 6,
+        };
+        assert_eq!(rule.probability(0), 0.0);
+        assert_eq!(rule.probability(2), 0.0);
+        assert_eq!(rule.probability(4), 0.5);
+        assert_eq!(rule.probability(6), 1.0);
+        assert_eq!(rule.probability(9), 1.0);
+    }
+
+    #[test]
+    fn test_table_probability_extends_last_entry() {
+        let rule = BalkingRule::Table {
+            probabilities: vec![0.0, 0.1, 0.4],
+        };
+        assert_eq!(rule.probability(1), 0.1);
+        assert_eq!(rule.probability(2), 0.4);
+        assert_eq!(rule.probability(7), 0.4);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(
+            BalkingRule::Linear {
+                min_queue: 5,
+                max_queue: 5
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            BalkingRule::Table {
+                probabilities: vec![]
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            BalkingRule::Table {
+                probabilities: vec![0.5, 1.5]
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(BalkingRule::Threshold { max_queue: 0 }.validate().is_ok());
+    }
+}
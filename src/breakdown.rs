@@ -0,0 +1,71 @@
+use crate::distribution::ServiceDistribution;
+use serde::Deserialize;
+
+/// What happens to the customer being served when a window breaks down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interruption {
+    /// Service continues where it stopped once the window is repaired (preempt-resume)
+    #[default]
+    Resume,
+    /// Service starts over once the window is repaired (preempt-restart)
+    Restart,
+}
+
+impl Interruption {
+    /// Returns the name used in the configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Resume => "resume",
+            Self::Restart => "restart",
+        }
+    }
+}
+
+/// Failure process of a window (e.g. a payment terminal or headset failing)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Breakdowns {
+    /// Time from a repair (or the start of the run) to the next failure
+    pub time_between_failures: ServiceDistribution,
+    /// Time the window is out of service after a failure
+    pub repair_time: ServiceDistribution,
+    /// What happens to the customer at the window when it fails
+    #[serde(default)]
+    pub interruption: Interruption,
+}
+
+impl Breakdowns {
+    /// Checks that both distributions are valid
+    pub fn validate(&self) -> Result<(), String> {
+        self.time_between_failures
+            .validate()
+            .map_err(|e| format!("breakdowns.time_between_failures: {}", e))?;
+        self.repair_time
+            .validate()
+            .map_err(|e| format!("breakdowns.repair_time: {}", e))
+    }
+
+    /// Returns the long-run fraction of time a window is in service, MTBF / (MTBF + MTTR)
+    pub fn availability(&self) -> f64 {
+        let up = self.time_between_failures.mean();
+        up / (up + self.repair_time.mean())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let mut breakdowns = Breakdowns {
+            time_between_failures: ServiceDistribution::Exponential { mean: 3600.0 },
+            repair_time: ServiceDistribution::Deterministic { value: 300.0 },
+            interruption: Interruption::default(),
+        };
+        assert!(breakdowns.validate().is_ok());
+        assert!((breakdowns.availability() - 3600.0 / 3900.0).abs() < 1e-12);
+        breakdowns.repair_time = ServiceDistribution::Deterministic { value: 0.0 };
+        assert!(breakdowns.validate().is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::distribution::ServiceDistribution;
+use rand::Rng;
+use serde::Deserialize;
+
+fn default_share() -> f64 {
+    1.0
+}
+
+/// A group of customers with their own arrival share, service time and priority
+/// (e.g. mobile-order pickups vs. regular orders)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CustomerClass {
+    pub name: String,
+    /// Relative share of arrivals belonging to the class (normalized over all classes)
+    #[serde(default = "default_share")]
+    pub share: f64,
+    /// Service time distribution of the class; required for randomly generated customers
+    #[serde(default)]
+    pub service: Option<ServiceDistribution>,
+    /// Waiting customers of a higher priority are served first; equal priorities are served
+    /// in arrival order
+    #[serde(default)]
+    pub priority: u32,
+}
+
+impl CustomerClass {
+    /// Checks that the class parameters are consistent
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("class: name must not be empty".into());
+        }
+        if !(self.share.is_finite() && self.share > 0.0) {
+            return Err(format!("class '{}': share must be positive", self.name));
+        }
+        if let Some(service) = &self.service {
+            service
+                .validate()
+                .map_err(|e| format!("class '{}': {}", self.name, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks every class and that class names are unique
+pub fn validate_classes(classes: &[CustomerClass]) -> Result<(), String> {
+    for (i, class) in classes.iter().enumerate() {
+        class.validate()?;
+        if classes[..i].iter().any(|c| c.name == class.name) {
+            return Err(format!("class '{}' is defined twice", class.name));
+        }
+    }
+    Ok(())
+}
+
+/// Draws the index of a class with probability proportional to its share
+///
+/// # Panics
+/// Panics if `classes` is empty
+pub fn choose_class<R: Rng + ?Sized>(rng: &mut R, classes: &[CustomerClass]) -> usize {
+    assert!(!classes.is_empty(), "At least one class is required");
+    let total: f64 = classes.iter().map(|c| c.share).sum();
+    let mut target = rng.random::<f64>() * total;
+    for (i, class) in classes.iter().enumerate() {
+        if target < class.share {
+            return i;
+        }
+        target -= class.share;
+    }
+    classes.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn class(name: &str, share: f64) -> CustomerClass {
+        CustomerClass {
+            name: name.into(),
+            share,
+            service: None,
+            priority: 0,
+        }
+    }
+
+    #[test]
+    fn test_choose_class_follows_shares() {
+        let classes = [class("mobile", 1.0), class("regular", 3.0)];
+        let mut rng = StdRng::seed_from_u64(5);
+
+        let mut counts = [0usize; 2];
+        for _ in 0..20_000 {
+            counts[choose_class(&mut rng, &classes)] += 1;
+        }
+
+        let mobile = counts[0] as f64 / 20_000.0;
+        assert!((mobile - 0.25).abs() < 0.02, "mobile share: {}", mobile);
+    }
+
+    #[test]
+    fn test_validate_classes() {
+        assert!(validate_classes(&[class("a", 1.0), class("b", 0.5)]).is_ok());
+        assert!(validate_classes(&[class("a", 1.0), class("a", 1.0)]).is_err());
+        assert!(validate_classes(&[class("a", 0.0)]).is_err());
+        assert!(validate_classes(&[class("", 1.0)]).is_err());
+    }
+}
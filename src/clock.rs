@@ -2,6 +2,7 @@ use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll, Waker};
 
@@ -9,6 +10,8 @@ use std::task::{Context, Poll, Waker};
 ///
 /// This clock allows tasks to sleep until specific times, and advances
 /// time in discrete steps based on scheduled wake events.
+/// Sleeps that are dropped before completing (e.g. in an aborted task)
+/// are cancelled and never advance the clock.
 pub struct SimClock {
     inner: Arc<Mutex<ClockInner>>,
 }
@@ -22,6 +25,7 @@ struct ClockInner {
 struct WakeEvent {
     time: f64,
     waker: Waker,
+    cancelled: Arc<AtomicBool>,
 }
 
 impl PartialEq for WakeEvent {
@@ -66,9 +70,10 @@ impl SimClock {
     }
 
     /// Sleeps for the specified duration in simulation time
+    ///
+    /// Returns immediately if the duration is not positive.
     pub async fn sleep(&self, duration: f64) {
         if duration <= 0.0 {
-            tokio::task::yield_now().await;
             return;
         }
         let wake_time = self.now() + duration;
@@ -76,17 +81,21 @@ impl SimClock {
     }
 
     /// Sleeps until the specified absolute time in simulation time
+    ///
+    /// Returns immediately if the time has already been reached. Yielding instead would
+    /// give the clock a chance to advance before the task registers its next wake-up
+    /// (e.g. several customers arriving at the same time).
     pub async fn sleep_until(&self, wake_time: f64) {
         let now = self.now();
         if wake_time <= now {
-            tokio::task::yield_now().await;
             return;
         }
 
         SleepFuture {
             clock: self.inner.clone(),
             wake_time,
-            registered: false,
+            cancelled: None,
+            completed: false,
         }
         .await;
     }
@@ -96,20 +105,81 @@ impl SimClock {
     /// Returns true if time was advanced, false if no events remain
     pub fn advance(&self) -> bool {
         let mut inner = self.inner.lock().unwrap();
-        if let Some(Reverse(event)) = inner.wakers.pop() {
+        while let Some(Reverse(event)) = inner.wakers.pop() {
+            if event.cancelled.load(Ordering::Relaxed) {
+                continue;
+            }
             inner.now = event.time;
             event.waker.wake();
             while let Some(Reverse(peek)) = inner.wakers.peek() {
                 if peek.time <= inner.now {
                     let Reverse(e) = inner.wakers.pop().unwrap();
-                    e.waker.wake();
+                    if !e.cancelled.load(Ordering::Relaxed) {
+                        e.waker.wake();
+                    }
                 } else {
                     break;
                 }
             }
-            true
-        } else {
-            false
+            return true;
+        }
+        false
+    }
+
+    /// Returns the time of the next scheduled event, skipping cancelled sleeps
+    fn next_event_time(&self) -> Option<f64> {
+        let mut inner = self.inner.lock().unwrap();
+        while let Some(Reverse(event)) = inner.wakers.peek() {
+            if !event.cancelled.load(Ordering::Relaxed) {
+                return Some(event.time);
+            }
+            inner.wakers.pop();
+        }
+        None
+    }
+
+    /// Advances the clock from event to event until no events remain or `max_time` is reached
+    ///
+    /// Events after `max_time` are never processed; the clock stops exactly at `max_time`.
+    /// Must be awaited inside the `LocalSet` running the simulation tasks, yielding to them
+    /// between steps. `customers_in_system` is consulted when the clock runs dry, to warn
+    /// about customers stuck in the system (deadlock).
+    pub async fn run(&self, max_time: Option<f64>, customers_in_system: impl Fn() -> usize) {
+        let mut no_advance_count = 0;
+        const MAX_NO_ADVANCE: usize = 100;
+
+        loop {
+            tokio::task::yield_now().await;
+
+            if max_time.is_some_and(|limit| self.now() >= limit) {
+                break;
+            }
+            if let Some(limit) = max_time
+                && self.next_event_time().is_some_and(|time| time > limit)
+            {
+                self.inner.lock().unwrap().now = limit;
+                break;
+            }
+
+            if !self.advance() {
+                // No events to advance - check if we're deadlocked or truly done
+                no_advance_count += 1;
+
+                if no_advance_count > MAX_NO_ADVANCE {
+                    // Potential deadlock - check if there are customers still in system
+                    let in_system = customers_in_system();
+                    if in_system > 0 {
+                        eprintln!(
+                            "Warning: Deadlock detected with {} customers still in system",
+                            in_system
+                        );
+                    }
+                    break;
+                }
+            } else {
+                // Successfully advanced, reset counter
+                no_advance_count = 0;
+            }
         }
     }
 }
@@ -120,6 +190,47 @@ impl Default for SimClock {
     }
 }
 
+struct SleepFuture {
+    clock: Arc<Mutex<ClockInner>>,
+    wake_time: f64,
+    /// Set once the wake event is registered; flagged if the future is dropped early
+    cancelled: Option<Arc<AtomicBool>>,
+    completed: bool,
+}
+
+impl Future for SleepFuture {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut inner = this.clock.lock().unwrap();
+        if inner.now >= this.wake_time {
+            this.completed = true;
+            Poll::Ready(())
+        } else {
+            if this.cancelled.is_none() {
+                let cancelled = Arc::new(AtomicBool::new(false));
+                inner.wakers.push(Reverse(WakeEvent {
+                    time: this.wake_time,
+                    waker: cx.waker().clone(),
+                    cancelled: cancelled.clone(),
+                }));
+                this.cancelled = Some(cancelled);
+            }
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for SleepFuture {
+    fn drop(&mut self) {
+        if !self.completed
+            && let Some(cancelled) = &self.cancelled
+        {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,30 +294,95 @@ mod tests {
             assert_eq!(*collected_times, vec![10.0, 20.0, 30.0]);
         }).await;
     }
-}
 
-struct SleepFuture {
-    clock: Arc<Mutex<ClockInner>>,
-    wake_time: f64,
-    registered: bool,
-}
+    #[tokio::test]
+    async fn test_aborted_sleep_does_not_advance_clock() {
+        let local = tokio::task::LocalSet::new();
 
-impl Future for SleepFuture {
-    type Output = ();
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.get_mut();
-        let mut inner = this.clock.lock().unwrap();
-        if inner.now >= this.wake_time {
-            Poll::Ready(())
-        } else {
-            if !this.registered {
-                inner.wakers.push(Reverse(WakeEvent {
-                    time: this.wake_time,
-                    waker: cx.waker().clone(),
-                }));
-                this.registered = true;
-            }
-            Poll::Pending
-        }
+        local.run_until(async {
+            let clock = Arc::new(SimClock::new());
+
+                let short = clock.clone();
+                tokio::task::spawn_local(async move {
+                    short.sleep_until(10.0).await;
+                });
+                let long = clock.clone();
+                let aborted = tokio::task::spawn_local(async move {
+                    long.sleep_until(50.0).await;
+                });
+
+                // Yield to allow both tasks to register their wakers, then cancel the long sleep
+                tokio::task::yield_now().await;
+                aborted.abort();
+                tokio::task::yield_now().await;
+
+            assert!(clock.advance());
+            assert_eq!(clock.now(), 10.0);
+            tokio::task::yield_now().await;
+
+                // The cancelled wake event at 50.0 must be skipped
+                assert!(!clock.advance());
+                assert_eq!(clock.now(), 10.0);
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_elapsed_sleeps_do_not_let_clock_skip_ahead() {
+        let local = tokio::task::LocalSet::new();
+
+        local
+            .run_until(async {
+                let clock = Arc::new(SimClock::new());
+                let times = Arc::new(Mutex::new(Vec::new()));
+
+                // Several events at the current time followed by one at t=1
+                let arrivals = clock.clone();
+                let arrival_times = times.clone();
+                tokio::task::spawn_local(async move {
+                    for wake_time in [0.0, 0.0, 0.0, 1.0] {
+                        arrivals.sleep_until(wake_time).await;
+                        arrival_times.lock().unwrap().push(arrivals.now());
+                    }
+                });
+                let other = clock.clone();
+                let other_times = times.clone();
+                tokio::task::spawn_local(async move {
+                    other.sleep_until(10.0).await;
+                    other_times.lock().unwrap().push(other.now());
+                });
+
+                clock.run(None, || 0).await;
+
+                assert_eq!(*times.lock().unwrap(), vec![0.0, 0.0, 0.0, 1.0, 10.0]);
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_at_max_time() {
+        let local = tokio::task::LocalSet::new();
+
+        local
+            .run_until(async {
+                let clock = Arc::new(SimClock::new());
+                let times = Arc::new(Mutex::new(Vec::new()));
+
+                let sleeper = clock.clone();
+                let sleeper_times = times.clone();
+                tokio::task::spawn_local(async move {
+                    for wake_time in [5.0, 15.0] {
+                        sleeper.sleep_until(wake_time).await;
+                        sleeper_times.lock().unwrap().push(sleeper.now());
+                    }
+                });
+
+                clock.run(Some(10.0), || 0).await;
+
+                // The event at t=15 is never processed and the clock does not pass the limit
+                assert_eq!(*times.lock().unwrap(), vec![5.0]);
+                assert_eq!(clock.now(), 10.0);
+            })
+            .await;
     }
 }
@@ -0,0 +1,22 @@
+use serde::Deserialize;
+
+/// What happens at closing time (the end of the run)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClosingMode {
+    /// Everything stops at closing time; customers still in line are not served
+    #[default]
+    Hard,
+    /// No customer arrives after closing time, but everyone already in line is served
+    Drain,
+}
+
+impl ClosingMode {
+    /// Returns the name used in the configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Hard => "hard",
+            Self::Drain => "drain",
+        }
+    }
+}
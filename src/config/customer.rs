@@ -1,4 +1,4 @@
-use crate::duration::deserialize_duration;
+use crate::duration::{deserialize_duration, deserialize_optional_duration};
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -7,4 +7,10 @@ pub struct FixedCustomerConfig {
     pub arrival: f64,
     #[serde(deserialize_with = "deserialize_duration")]
     pub service: f64,
+    /// Time after which the customer leaves the queue if not yet served
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub patience: Option<f64>,
+    /// Name of the customer's class (see `classes`)
+    #[serde(default)]
+    pub class: Option<String>,
 }
@@ -1,10 +1,221 @@
 use super::customer::FixedCustomerConfig;
+use crate::analytics::{OfferedLoad, OverloadPolicy, check_overload, window_capacities};
+use crate::balking::BalkingRule;
+use crate::breakdown::Breakdowns;
+use crate::class::{CustomerClass, validate_classes};
+use crate::closing::ClosingMode;
+use crate::cost::CostModel;
+use crate::distribution::ServiceDistribution;
+use crate::duration::{deserialize_optional_duration, format_duration};
+use crate::histogram::HistogramBins;
+use crate::lane::LaneOverflow;
+use crate::queue::QueueDiscipline;
+use crate::routing::WindowLanes;
+use crate::staffing::{Shift, StaffingSchedule};
+use crate::timeseries::TimeBuckets;
+use crate::window::{Window, validate_windows};
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
 pub struct FixedSimConfig {
     pub enabled: bool,
+    /// Number of service windows (may be omitted when `windows` are defined)
+    #[serde(default)]
     pub num_windows: usize,
     pub customers: Vec<FixedCustomerConfig>,
     pub history_file: String,
+    /// CSV file with one row per customer (not written when omitted)
+    #[serde(default)]
+    pub customer_file: Option<String>,
+    /// Time after which no customer arrives (runs until everyone is served when omitted)
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub closing_time: Option<f64>,
+    /// Seed for the random decisions (balking, patience, breakdowns); a random seed is chosen
+    /// when omitted
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Whether the run stops at `closing_time` or serves the customers still in line
+    #[serde(default)]
+    pub closing: ClosingMode,
+    /// Bins of the waiting time and time in system histograms (no histograms when omitted)
+    #[serde(default)]
+    pub histogram: Option<HistogramBins>,
+    /// Statistics per time bucket in the report and, optionally, a CSV file (none when omitted)
+    #[serde(default)]
+    pub time_series: Option<TimeBuckets>,
+    /// Prices turning the results into revenue, costs and profit (no cost report when omitted)
+    #[serde(default)]
+    pub costs: Option<CostModel>,
+    /// Optional rule by which arriving customers drive past a long queue
+    #[serde(default)]
+    pub balking: Option<BalkingRule>,
+    /// Patience distribution for customers without an explicit `patience`
+    #[serde(default)]
+    pub patience: Option<ServiceDistribution>,
+    /// Maximum number of cars waiting in the lane (unlimited when omitted)
+    #[serde(default)]
+    pub lane_capacity: Option<usize>,
+    /// What happens to arriving cars when the lane is full
+    #[serde(default)]
+    pub lane_overflow: LaneOverflow,
+    /// Customer classes; customers refer to them by name (`share` and `service` are unused)
+    #[serde(default)]
+    pub classes: Vec<CustomerClass>,
+    /// Order in which waiting customers are served
+    #[serde(default)]
+    pub queue_discipline: QueueDiscipline,
+    /// A separate lane per window with routing and jockeying (one shared queue when omitted)
+    #[serde(default)]
+    pub window_lanes: Option<WindowLanes>,
+    /// Definitions of the individual windows (speed, served classes)
+    #[serde(default)]
+    pub windows: Vec<Window>,
+    /// Shifts with the number of windows open; every window is open outside of them
+    #[serde(default)]
+    pub staffing: Vec<Shift>,
+    /// Failure process of every window without its own (no breakdowns when omitted)
+    #[serde(default)]
+    pub breakdowns: Option<Breakdowns>,
+    /// Whether an offered load at or above the capacity of the windows only prints a
+    /// warning or stops the configuration from loading
+    #[serde(default)]
+    pub overload: OverloadPolicy,
+}
+
+impl FixedSimConfig {
+    /// Checks the customers, the windows and every optional setting, and the offered load
+    pub fn validate(&self) -> Result<(), String> {
+        self.validate_customers()?;
+        self.validate_windows()?;
+        self.validate_reports()?;
+        check_overload(&self.offered_load(), self.overload)
+            .map_err(|e| format!("fixed_simulation: {}", e))
+    }
+
+    /// Checks the customers and the rules of how they behave in the lane
+    fn validate_customers(&self) -> Result<(), String> {
+        if let Some(rule) = &self.balking {
+            rule.validate()
+                .map_err(|e| format!("fixed_simulation.{}", e))?;
+        }
+        if let Some(dist) = &self.patience {
+            dist.validate()
+                .map_err(|e| format!("fixed_simulation.patience: {}", e))?;
+        }
+        if self
+            .customers
+            .iter()
+            .any(|c| c.patience.is_some_and(|p| p < 0.0))
+        {
+            return Err("fixed_simulation: customer patience must be non-negative".into());
+        }
+        if self.lane_capacity == Some(0) {
+            return Err("fixed_simulation.lane_capacity must be greater than 0".into());
+        }
+        if self.closing_time.is_some_and(|t| t <= 0.0) {
+            return Err("fixed_simulation.closing_time must be positive".into());
+        }
+        validate_classes(&self.classes).map_err(|e| format!("fixed_simulation.{}", e))?;
+        for customer in &self.customers {
+            self.class_index(customer)?;
+        }
+        Ok(())
+    }
+
+    /// Checks the windows, their lanes, staffing and breakdowns
+    fn validate_windows(&self) -> Result<(), String> {
+        if let Some(lanes) = &self.window_lanes {
+            lanes
+                .validate()
+                .map_err(|e| format!("fixed_simulation.{}", e))?;
+        }
+        validate_windows(&self.windows, &self.classes)
+            .map_err(|e| format!("fixed_simulation.{}", e))?;
+        if !self.windows.is_empty()
+            && self.num_windows != 0
+            && self.num_windows != self.windows.len()
+        {
+            return Err(
+                "fixed_simulation: num_windows does not match the number of windows".into(),
+            );
+        }
+        if self.window_count() == 0 {
+            return Err("fixed_simulation.num_windows must be greater than 0".into());
+        }
+        StaffingSchedule::new(self.window_count(), self.staffing.clone())
+            .map_err(|e| format!("fixed_simulation.staffing: {}", e))?;
+        if let Some(breakdowns) = &self.breakdowns {
+            breakdowns
+                .validate()
+                .map_err(|e| format!("fixed_simulation.{}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Checks the optional parts of the report
+    fn validate_reports(&self) -> Result<(), String> {
+        if let Some(bins) = &self.histogram {
+            bins.validate()
+                .map_err(|e| format!("fixed_simulation.{}", e))?;
+        }
+        if let Some(buckets) = &self.time_series {
+            buckets
+                .validate()
+                .map_err(|e| format!("fixed_simulation.{}", e))?;
+        }
+        if let Some(costs) = &self.costs {
+            costs
+                .validate()
+                .map_err(|e| format!("fixed_simulation.{}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of windows: one per definition if `windows` are defined
+    pub fn window_count(&self) -> usize {
+        if self.windows.is_empty() {
+            self.num_windows
+        } else {
+            self.windows.len()
+        }
+    }
+
+    /// Returns the offered load of the customers between the first and the last arrival, with
+    /// every window open (empty with fewer than two arrival times)
+    pub fn offered_load(&self) -> Vec<OfferedLoad> {
+        let arrivals = self.customers.iter().map(|c| c.arrival);
+        let first = arrivals.clone().fold(f64::INFINITY, f64::min);
+        let last = arrivals.fold(f64::NEG_INFINITY, f64::max);
+        if last <= first {
+            return Vec::new();
+        }
+        let count = self.customers.len() as f64;
+        vec![OfferedLoad {
+            label: format!("{} - {}", format_duration(first), format_duration(last)),
+            arrival_rate: (count - 1.0) / (last - first),
+            mean_service: self.customers.iter().map(|c| c.service).sum::<f64>() / count,
+            capacity: window_capacities(
+                self.window_count(),
+                &self.windows,
+                self.breakdowns.as_ref(),
+            )
+            .iter()
+            .sum(),
+        }]
+    }
+
+    /// Returns the index of the class a customer belongs to (0 without a class)
+    pub fn class_index(&self, customer: &FixedCustomerConfig) -> Result<usize, String> {
+        match &customer.class {
+            None if self.classes.is_empty() => Ok(0),
+            None => Err(
+                "fixed_simulation: every customer needs a class when classes are configured".into(),
+            ),
+            Some(name) => self
+                .classes
+                .iter()
+                .position(|c| &c.name == name)
+                .ok_or_else(|| format!("fixed_simulation: unknown customer class '{}'", name)),
+        }
+    }
 }
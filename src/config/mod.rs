@@ -33,13 +33,19 @@ impl Config {
         Ok(config)
     }
 
-    /// Validates that at least one simulation is enabled
+    /// Validates that at least one simulation is enabled and that the enabled ones are well-formed
     fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
         if !self.fixed_simulation.enabled && !self.random_simulation.enabled {
             return Err(
                 "At least one simulation (fixed or random) must be enabled in config.yaml".into(),
             );
         }
+        if self.fixed_simulation.enabled {
+            self.fixed_simulation.validate()?;
+        }
+        if self.random_simulation.enabled {
+            self.random_simulation.validate()?;
+        }
         Ok(())
     }
 
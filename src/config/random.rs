@@ -1,17 +1,895 @@
-use crate::duration::deserialize_duration;
+use crate::analytics::{
+    OfferedLoad, OverloadPolicy, QueueingModel, check_overload, window_capacities,
+};
+use crate::arrival::{ArrivalProfile, ArrivalSegment};
+use crate::balking::BalkingRule;
+use crate::breakdown::Breakdowns;
+use crate::class::{CustomerClass, validate_classes};
+use crate::closing::ClosingMode;
+use crate::cost::CostModel;
+use crate::distribution::ServiceDistribution;
+use crate::duration::{deserialize_duration, deserialize_optional_duration, format_duration};
+use crate::histogram::HistogramBins;
+use crate::lane::LaneOverflow;
+use crate::optimizer::StaffingOptimizer;
+use crate::queue::QueueDiscipline;
+use crate::routing::WindowLanes;
+use crate::staffing::{Shift, StaffingSchedule};
+use crate::sweep::{DesignPoint, Sweep};
+use crate::tandem::{Station, validate_stations};
+use crate::timeseries::TimeBuckets;
+use crate::window::{Window, validate_windows};
 use serde::Deserialize;
 
+fn default_replications() -> usize {
+    1
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RandomSimConfig {
     pub enabled: bool,
+    /// Number of service windows (ignored when `stations` are configured; may be omitted when
+    /// `windows` are defined)
+    #[serde(default)]
     pub num_windows: usize,
     #[serde(deserialize_with = "deserialize_duration")]
     pub avg_arrival_interval: f64,
-    #[serde(deserialize_with = "deserialize_duration")]
-    pub min_service_time: f64,
-    #[serde(deserialize_with = "deserialize_duration")]
-    pub max_service_time: f64,
+    /// Periods with a different arrival rate (e.g. lunch and dinner rushes)
+    #[serde(default)]
+    pub arrival_profile: Vec<ArrivalSegment>,
+    /// Service time distribution; takes precedence over `min_service_time`/`max_service_time`
+    #[serde(default)]
+    pub service_distribution: Option<ServiceDistribution>,
+    /// Shorthand for a uniform service distribution (requires `max_service_time`)
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub min_service_time: Option<f64>,
+    /// Shorthand for a uniform service distribution (requires `min_service_time`)
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub max_service_time: Option<f64>,
+    /// Closing time: customers arrive until then
     #[serde(deserialize_with = "deserialize_duration")]
     pub max_simulation_time: f64,
+    /// Whether the run stops at `max_simulation_time` or serves the customers still in line
+    #[serde(default)]
+    pub closing: ClosingMode,
+    /// Period at the start deleted from the statistics, as the lane starts empty
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub warmup: Option<f64>,
+    /// Detects the warm-up period with MSER-5 and reports it with the statistics
+    #[serde(default)]
+    pub detect_warmup: bool,
+    /// Bins of the waiting time and time in system histograms (no histograms when omitted)
+    #[serde(default)]
+    pub histogram: Option<HistogramBins>,
+    /// Statistics per time bucket in the report and, optionally, a CSV file (none when omitted)
+    #[serde(default)]
+    pub time_series: Option<TimeBuckets>,
+    /// Prices turning the results into revenue, costs and profit (no cost report when omitted)
+    #[serde(default)]
+    pub costs: Option<CostModel>,
     pub history_file: String,
+    /// CSV file with one row per customer, written by single runs (not written when omitted)
+    #[serde(default)]
+    pub customer_file: Option<String>,
+    /// Seed for the random number generator; a random seed is chosen when omitted
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Number of independent replications, run with the seeds `seed`, `seed + 1`, ...
+    #[serde(default = "default_replications")]
+    pub replications: usize,
+    /// CSV file for the summary of the replications (not written when omitted)
+    #[serde(default)]
+    pub replications_file: Option<String>,
+    /// Capacity study running replications of every combination of the listed values
+    #[serde(default)]
+    pub sweep: Option<Sweep>,
+    /// Search for the fewest windows meeting a service level
+    #[serde(default)]
+    pub optimize: Option<StaffingOptimizer>,
+    /// Optional rule by which arriving customers drive past a long queue
+    #[serde(default)]
+    pub balking: Option<BalkingRule>,
+    /// Distribution of how long customers wait in the queue before leaving (reneging)
+    #[serde(default)]
+    pub patience: Option<ServiceDistribution>,
+    /// Maximum number of cars waiting in the lane (unlimited when omitted)
+    #[serde(default)]
+    pub lane_capacity: Option<usize>,
+    /// What happens to arriving cars when the lane is full
+    #[serde(default)]
+    pub lane_overflow: LaneOverflow,
+    /// Stations every customer passes through in order (e.g. order, pay, pickup);
+    /// when set, replaces the single-stage model with `num_windows` windows
+    #[serde(default)]
+    pub stations: Vec<Station>,
+    /// Customer classes with their share of arrivals, service distribution and priority;
+    /// when set, replaces `service_distribution`
+    #[serde(default)]
+    pub classes: Vec<CustomerClass>,
+    /// Order in which waiting customers are served
+    #[serde(default)]
+    pub queue_discipline: QueueDiscipline,
+    /// A separate lane per window with routing and jockeying (one shared queue when omitted)
+    #[serde(default)]
+    pub window_lanes: Option<WindowLanes>,
+    /// Definitions of the individual windows (speed, served classes)
+    #[serde(default)]
+    pub windows: Vec<Window>,
+    /// Shifts with the number of windows open; every window is open outside of them
+    #[serde(default)]
+    pub staffing: Vec<Shift>,
+    /// Failure process of every window without its own (no breakdowns when omitted)
+    #[serde(default)]
+    pub breakdowns: Option<Breakdowns>,
+    /// Whether an offered load at or above the capacity of the windows only prints a
+    /// warning or stops the configuration from loading
+    #[serde(default)]
+    pub overload: OverloadPolicy,
+}
+
+impl RandomSimConfig {
+    /// Checks the single-stage or multi-stage model, every optional setting and the offered
+    /// load
+    pub fn validate(&self) -> Result<(), String> {
+        if self.replications == 0 {
+            return Err("random_simulation.replications must be greater than 0".into());
+        }
+        if self.is_multi_stage() {
+            self.validate_stations()?;
+        } else {
+            self.validate_windows()?;
+            self.validate_experiments()?;
+            self.validate_reports()?;
+            self.validate_customers()?;
+        }
+        check_overload(&self.offered_load()?, self.overload)
+            .map_err(|e| format!("random_simulation: {}", e))
+    }
+
+    /// Checks the stations and rejects the settings of the single-stage model
+    fn validate_stations(&self) -> Result<(), String> {
+        validate_stations(&self.stations)
+            .map_err(|e| format!("random_simulation.stations: {}", e))?;
+        if self.balking.is_some() || self.patience.is_some() || self.lane_capacity.is_some() {
+            return Err(
+                "random_simulation: balking, patience and lane_capacity are not supported with stations (use the first station's buffer as the lane)"
+                    .into(),
+            );
+        }
+        if !self.classes.is_empty() {
+            return Err("random_simulation: classes are not supported with stations".into());
+        }
+        if self.closing != ClosingMode::default() {
+            return Err("random_simulation: closing is not supported with stations".into());
+        }
+        if self.replications > 1 || self.sweep.is_some() || self.optimize.is_some() {
+            return Err(
+                "random_simulation: replications, sweep and optimize are not supported with stations"
+                    .into(),
+            );
+        }
+        if self.warmup.is_some()
+            || self.detect_warmup
+            || self.histogram.is_some()
+            || self.customer_file.is_some()
+            || self.time_series.is_some()
+            || self.costs.is_some()
+        {
+            return Err(
+                "random_simulation: warmup, detect_warmup, histogram, customer_file, time_series and costs are not supported with stations"
+                    .into(),
+            );
+        }
+        if self.queue_discipline != QueueDiscipline::default()
+            || self.window_lanes.is_some()
+            || !self.windows.is_empty()
+            || !self.staffing.is_empty()
+            || self.breakdowns.is_some()
+        {
+            return Err(
+                "random_simulation: queue_discipline, window_lanes, windows, staffing and breakdowns are not supported with stations"
+                    .into(),
+            );
+        }
+        self.arrival_profile()?;
+        Ok(())
+    }
+
+    /// Checks the windows, their lanes, staffing and breakdowns
+    fn validate_windows(&self) -> Result<(), String> {
+        validate_windows(&self.windows, &self.classes)
+            .map_err(|e| format!("random_simulation.{}", e))?;
+        if !self.windows.is_empty()
+            && self.num_windows != 0
+            && self.num_windows != self.windows.len()
+        {
+            return Err(
+                "random_simulation: num_windows does not match the number of windows".into(),
+            );
+        }
+        if self.window_count() == 0 {
+            return Err("random_simulation.num_windows must be greater than 0".into());
+        }
+        StaffingSchedule::new(self.window_count(), self.staffing.clone())
+            .map_err(|e| format!("random_simulation.staffing: {}", e))?;
+        if let Some(breakdowns) = &self.breakdowns {
+            breakdowns
+                .validate()
+                .map_err(|e| format!("random_simulation.{}", e))?;
+        }
+        if let Some(lanes) = &self.window_lanes {
+            lanes
+                .validate()
+                .map_err(|e| format!("random_simulation.{}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Checks the sweep and the staffing optimizer and the settings they cannot be combined with
+    fn validate_experiments(&self) -> Result<(), String> {
+        if let Some(sweep) = &self.sweep {
+            sweep
+                .validate()
+                .map_err(|e| format!("random_simulation.{}", e))?;
+            if !sweep.num_windows.is_empty() && !self.windows.is_empty() {
+                return Err(
+                    "random_simulation: sweep.num_windows cannot be combined with windows".into(),
+                );
+            }
+            if !sweep.service_distribution.is_empty() && !self.classes.is_empty() {
+                return Err(
+                    "random_simulation: sweep.service_distribution cannot be combined with classes"
+                        .into(),
+                );
+            }
+            for &num_windows in &sweep.num_windows {
+                StaffingSchedule::new(num_windows, self.staffing.clone())
+                    .map_err(|e| format!("random_simulation.staffing: {}", e))?;
+            }
+        }
+        if let Some(optimize) = &self.optimize {
+            optimize
+                .validate()
+                .map_err(|e| format!("random_simulation.optimize: {}", e))?;
+            if self.sweep.is_some() {
+                return Err("random_simulation: optimize cannot be combined with sweep".into());
+            }
+            if !self.windows.is_empty() {
+                return Err("random_simulation: optimize cannot be combined with windows".into());
+            }
+            if !self.staffing.is_empty() {
+                return Err("random_simulation: optimize cannot be combined with staffing".into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the optional parts of the report and the warm-up period
+    fn validate_reports(&self) -> Result<(), String> {
+        if let Some(bins) = &self.histogram {
+            bins.validate()
+                .map_err(|e| format!("random_simulation.{}", e))?;
+        }
+        if let Some(buckets) = &self.time_series {
+            buckets
+                .validate()
+                .map_err(|e| format!("random_simulation.{}", e))?;
+        }
+        if let Some(costs) = &self.costs {
+            costs
+                .validate()
+                .map_err(|e| format!("random_simulation.{}", e))?;
+        }
+        if self
+            .warmup
+            .is_some_and(|t| t <= 0.0 || t >= self.max_simulation_time)
+        {
+            return Err(
+                "random_simulation.warmup must be positive and shorter than max_simulation_time"
+                    .into(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks the arrivals, the service times and the rules of how customers behave in the lane
+    fn validate_customers(&self) -> Result<(), String> {
+        if let Some(rule) = &self.balking {
+            rule.validate()
+                .map_err(|e| format!("random_simulation.{}", e))?;
+        }
+        if let Some(dist) = &self.patience {
+            dist.validate()
+                .map_err(|e| format!("random_simulation.patience: {}", e))?;
+        }
+        if self.lane_capacity == Some(0) {
+            return Err("random_simulation.lane_capacity must be greater than 0".into());
+        }
+        self.arrival_profile()?;
+        if self.classes.is_empty() {
+            self.service_distribution()?;
+        } else {
+            validate_classes(&self.classes).map_err(|e| format!("random_simulation.{}", e))?;
+            if let Some(class) = self.classes.iter().find(|c| c.service.is_none()) {
+                return Err(format!(
+                    "random_simulation: class '{}' needs a service distribution",
+                    class.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the arrival rate profile from `avg_arrival_interval` and `arrival_profile`
+    pub fn arrival_profile(&self) -> Result<ArrivalProfile, String> {
+        ArrivalProfile::new(self.avg_arrival_interval, self.arrival_profile.clone())
+            .map_err(|e| format!("random_simulation.arrival_profile: {}", e))
+    }
+
+    /// Returns the number of windows: one per definition if `windows` are defined
+    pub fn window_count(&self) -> usize {
+        if self.windows.is_empty() {
+            self.num_windows
+        } else {
+            self.windows.len()
+        }
+    }
+
+    /// Returns the configured values of the parameters a sweep can vary
+    pub fn design_point(&self) -> Result<DesignPoint, String> {
+        let service = if self.classes.is_empty() {
+            Some(self.service_distribution()?)
+        } else {
+            None
+        };
+        Ok(DesignPoint {
+            num_windows: self.window_count(),
+            avg_arrival_interval: self.avg_arrival_interval,
+            service,
+        })
+    }
+
+    /// Returns the queueing model of `point` if the configuration is one queueing theory
+    /// covers: constant-rate Poisson arrivals, one service time distribution and identical
+    /// windows always open, sharing one queue that every customer joins and stays in
+    pub fn queueing_model(&self, point: &DesignPoint) -> Option<QueueingModel> {
+        let plain = self.arrival_profile.is_empty()
+            && !self.is_multi_stage()
+            && self.balking.is_none()
+            && self.patience.is_none()
+            && self.lane_capacity.is_none()
+            && self.window_lanes.is_none()
+            && self.windows.is_empty()
+            && self.staffing.is_empty()
+            && self.breakdowns.is_none()
+            // Serving short orders first changes the average wait
+            && self.queue_discipline != QueueDiscipline::ShortestProcessingTime;
+        let service = point.service.as_ref().filter(|_| plain)?;
+        Some(QueueingModel::new(
+            point.avg_arrival_interval,
+            service,
+            point.num_windows,
+        ))
+    }
+
+    /// Returns the offered load of every period between changes of the arrival rate and of
+    /// the staffing, up to `max_simulation_time` (per station with stations)
+    ///
+    /// A sweep is checked at every design point, and the staffing optimizer with all
+    /// `max_windows` windows open; their labels start with the design point.
+    pub fn offered_load(&self) -> Result<Vec<OfferedLoad>, String> {
+        if self.is_multi_stage() {
+            return self.station_loads();
+        }
+        let base = self.design_point()?;
+        if let Some(sweep) = &self.sweep {
+            let mut loads = Vec::new();
+            for point in sweep.design_points(&base) {
+                loads.extend(
+                    self.window_loads(&point)?
+                        .into_iter()
+                        .map(|load| OfferedLoad {
+                            label: format!("{}, {}", point.label(), load.label),
+                            ..load
+                        }),
+                );
+            }
+            return Ok(loads);
+        }
+        if let Some(optimizer) = &self.optimize {
+            let point = DesignPoint {
+                num_windows: optimizer.max_windows,
+                ..base
+            };
+            return Ok(self
+                .window_loads(&point)?
+                .into_iter()
+                .map(|load| OfferedLoad {
+                    label: format!("{} windows, {}", point.num_windows, load.label),
+                    ..load
+                })
+                .collect());
+        }
+        self.window_loads(&base)
+    }
+
+    /// Returns the offered load of the windows of `point` in every period
+    fn window_loads(&self, point: &DesignPoint) -> Result<Vec<OfferedLoad>, String> {
+        let arrivals =
+            ArrivalProfile::new(point.avg_arrival_interval, self.arrival_profile.clone())
+                .map_err(|e| format!("random_simulation.arrival_profile: {}", e))?;
+        let staffing = StaffingSchedule::new(point.num_windows, self.staffing.clone())
+            .map_err(|e| format!("random_simulation.staffing: {}", e))?;
+        let capacities =
+            window_capacities(point.num_windows, &self.windows, self.breakdowns.as_ref());
+        let capacity_at = |time: f64| capacities[..staffing.staffed_at(time)].iter().sum::<f64>();
+        let mean_service = if let Some(service) = &point.service {
+            service.mean()
+        } else {
+            let total_share: f64 = self.classes.iter().map(|c| c.share).sum();
+            self.classes
+                .iter()
+                .map(|c| c.share * c.service.as_ref().map_or(0.0, ServiceDistribution::mean))
+                .sum::<f64>()
+                / total_share
+        };
+        Ok(self
+            .load_periods(&arrivals, capacity_at)
+            .into_iter()
+            .map(|(from, to)| OfferedLoad {
+                label: period_label(from, to),
+                arrival_rate: arrivals.peak_rate(from, to),
+                mean_service,
+                capacity: capacity_at(from),
+            })
+            .collect())
+    }
+
+    /// Returns the offered load of every station in every period
+    fn station_loads(&self) -> Result<Vec<OfferedLoad>, String> {
+        let arrivals = self.arrival_profile()?;
+        let periods = self.load_periods(&arrivals, |_| 0.0);
+        Ok(self
+            .stations
+            .iter()
+            .flat_map(|station| {
+                periods.iter().map(|&(from, to)| OfferedLoad {
+                    label: format!("{}, {}", station.name, period_label(from, to)),
+                    arrival_rate: arrivals.peak_rate(from, to),
+                    mean_service: station.service.mean(),
+                    capacity: station.total_servers() as f64,
+                })
+            })
+            .collect())
+    }
+
+    /// Returns the periods between changes of the arrival rate and of the capacity, up to
+    /// `max_simulation_time`
+    fn load_periods(
+        &self,
+        arrivals: &ArrivalProfile,
+        capacity_at: impl Fn(f64) -> f64,
+    ) -> Vec<(f64, f64)> {
+        let mut bounds = vec![0.0, self.max_simulation_time];
+        bounds.extend(self.arrival_profile.iter().flat_map(|s| [s.from, s.to]));
+        bounds.extend(self.staffing.iter().flat_map(|s| [s.from, s.to]));
+        bounds.retain(|&t| t <= self.max_simulation_time);
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup();
+        // Neighbouring periods with the same rate and capacity are merged
+        let mut periods: Vec<(f64, f64)> = Vec::new();
+        for pair in bounds.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            match periods.last_mut() {
+                Some(last)
+                    if arrivals.peak_rate(last.0, last.1) == arrivals.peak_rate(from, to)
+                        && capacity_at(last.0) == capacity_at(from) =>
+                {
+                    last.1 = to;
+                }
+                _ => periods.push((from, to)),
+            }
+        }
+        periods
+    }
+
+    /// Returns true if customers pass through multiple stations
+    pub fn is_multi_stage(&self) -> bool {
+        !self.stations.is_empty()
+    }
+
+    /// Resolves the service time distribution
+    ///
+    /// Uses `service_distribution` when present, otherwise falls back to a uniform
+    /// distribution between `min_service_time` and `max_service_time`.
+    pub fn service_distribution(&self) -> Result<ServiceDistribution, String> {
+        let dist = match (
+            &self.service_distribution,
+            self.min_service_time,
+            self.max_service_time,
+        ) {
+            (Some(dist), _, _) => dist.clone(),
+            (None, Some(min), Some(max)) => ServiceDistribution::Uniform { min, max },
+            (None, _, _) => {
+                return Err(
+                    "random_simulation requires either service_distribution or both min_service_time and max_service_time"
+                        .into(),
+                );
+            }
+        };
+        dist.validate()
+            .map_err(|e| format!("random_simulation.service_distribution: {}", e))?;
+        Ok(dist)
+    }
+}
+
+/// Returns the label of the period from `from` to `to`
+fn period_label(from: f64, to: f64) -> String {
+    format!("{} - {}", format_duration(from), format_duration(to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breakdown::Interruption;
+    use crate::routing::RoutingPolicy;
+    use crate::tandem::MergePolicy;
+    use config::{Config, File, FileFormat};
+
+    fn parse(yaml: &str) -> RandomSimConfig {
+        Config::builder()
+            .add_source(File::from_str(yaml, FileFormat::Yaml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    const BASE: &str = r#"
+enabled: true
+num_windows: 2
+avg_arrival_interval: 40
+max_simulation_time: "1h"
+history_file: "out.csv"
+"#;
+
+    #[test]
+    fn test_min_max_shorthand_is_uniform() {
+        let cfg = parse(&format!(
+            "{BASE}min_service_time: 10\nmax_service_time: \"5min\"\n"
+        ));
+        assert_eq!(
+            cfg.service_distribution().unwrap(),
+            ServiceDistribution::Uniform {
+                min: 10.0,
+                max: 300.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_distribution_with_durations() {
+        let cfg = parse(&format!(
+            "{BASE}service_distribution: {{ type: erlang, k: 3, mean: \"1m 30s\" }}\n"
+        ));
+        assert_eq!(
+            cfg.service_distribution().unwrap(),
+            ServiceDistribution::Erlang { k: 3, mean: 90.0 }
+        );
+
+        let cfg = parse(&format!(
+            "{BASE}service_distribution: {{ type: empirical, samples: [30, \"1min\", \"2m 5s\"] }}\n"
+        ));
+        assert_eq!(
+            cfg.service_distribution().unwrap(),
+            ServiceDistribution::Empirical {
+                samples: vec![30.0, 60.0, 125.0]
+            }
+        );
+    }
+
+    #[test]
+    fn test_arrival_profile_segments() {
+        let cfg = parse(&format!(
+            "{BASE}min_service_time: 10\nmax_service_time: 60\narrival_profile:\n  - {{ name: lunch, from: \"11h30m\", to: \"13h30m\", interval: \"15s\" }}\n  - {{ from: \"17h\", to: \"19h\", interval: 20, end_interval: 40 }}\n"
+        ));
+        let profile = cfg.arrival_profile().unwrap();
+        let segments = profile.segments();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].label(), "lunch");
+        assert_eq!(segments[0].from, 41400.0);
+        assert_eq!(segments[0].to, 48600.0);
+        assert_eq!(segments[0].interval, 15.0);
+        assert_eq!(segments[1].end_interval, Some(40.0));
+    }
+
+    #[test]
+    fn test_closing() {
+        assert_eq!(parse(BASE).closing, ClosingMode::Hard);
+        assert_eq!(
+            parse(&format!("{BASE}closing: drain\n")).closing,
+            ClosingMode::Drain
+        );
+    }
+
+    #[test]
+    fn test_warmup() {
+        let cfg = parse(BASE);
+        assert_eq!(cfg.warmup, None);
+        assert!(!cfg.detect_warmup);
+        let cfg = parse(&format!("{BASE}warmup: \"30min\"\ndetect_warmup: true\n"));
+        assert_eq!(cfg.warmup, Some(1800.0));
+        assert!(cfg.detect_warmup);
+    }
+
+    #[test]
+    fn test_queueing_model() {
+        let cfg = parse(&format!(
+            "{BASE}service_distribution: {{ type: exponential, mean: 60 }}\n"
+        ));
+        let model = cfg.queueing_model(&cfg.design_point().unwrap()).unwrap();
+        assert_eq!(model.name(), "M/M/2, Erlang C");
+
+        let cfg = parse(&format!(
+            "{BASE}service_distribution: {{ type: exponential, mean: 60 }}\npatience: {{ type: exponential, mean: 300 }}\n"
+        ));
+        assert_eq!(cfg.queueing_model(&cfg.design_point().unwrap()), None);
+    }
+
+    #[test]
+    fn test_offered_load() {
+        let cfg = parse(&format!(
+            "{BASE}service_distribution: {{ type: exponential, mean: 60 }}\n"
+        ));
+        let loads = cfg.offered_load().unwrap();
+        assert_eq!(loads.len(), 1);
+        assert_eq!(loads[0].label, "0s - 1h");
+        assert!((loads[0].utilization() - 0.75).abs() < 1e-12);
+        assert_eq!(cfg.overload, OverloadPolicy::Warn);
+
+        // A lunch rush with one window closed: 60 / 20 / 1 = 300%
+        let cfg = parse(&format!(
+            "{BASE}service_distribution: {{ type: exponential, mean: 60 }}\noverload: refuse\narrival_profile:\n  - {{ from: \"20m\", to: \"40m\", interval: 20 }}\nstaffing:\n  - {{ from: \"30m\", to: \"2h\", windows: 1 }}\n"
+        ));
+        let loads = cfg.offered_load().unwrap();
+        let labels: Vec<&str> = loads.iter().map(|l| l.label.as_str()).collect();
+        assert_eq!(labels, ["0s - 20m", "20m - 30m", "30m - 40m", "40m - 1h"]);
+        assert!((loads[2].utilization() - 3.0).abs() < 1e-12);
+        assert_eq!(cfg.overload, OverloadPolicy::Refuse);
+
+        // Classes: (3 × 30 + 1 × 90) / 4 = 45 s
+        let cfg = parse(&format!(
+            "{BASE}classes:\n  - {{ name: mobile, share: 3, service: {{ type: deterministic, value: 30 }} }}\n  - {{ name: regular, service: {{ type: deterministic, value: 90 }} }}\n"
+        ));
+        assert!((cfg.offered_load().unwrap()[0].mean_service - 45.0).abs() < 1e-12);
+
+        // Sweeps are checked at every design point: 60 / 30 / 1 = 200%
+        let cfg = parse(&format!(
+            "{BASE}service_distribution: {{ type: deterministic, value: 60 }}\nsweep:\n  num_windows: [1, 3]\n  avg_arrival_interval: [30]\n  file: \"sweep.csv\"\n"
+        ));
+        let loads = cfg.offered_load().unwrap();
+        assert_eq!(loads.len(), 2);
+        assert!(loads[0].label.starts_with("1 windows, arrivals every 30s"));
+        assert!((loads[0].utilization() - 2.0).abs() < 1e-12);
+        assert!(!loads[1].is_overloaded());
+
+        // The staffing optimizer is checked with all of its windows
+        let cfg = parse(&format!(
+            "{BASE}service_distribution: {{ type: deterministic, value: 60 }}\noptimize:\n  max_windows: 4\n  sla: {{ percentile: 90, max_wait: \"2min\" }}\n"
+        ));
+        let loads = cfg.offered_load().unwrap();
+        assert_eq!(loads[0].label, "4 windows, 0s - 1h");
+        assert!((loads[0].capacity - 4.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let cfg = parse(BASE);
+        assert_eq!(cfg.histogram, None);
+        let cfg = parse(&format!(
+            "{BASE}histogram: {{ width: \"30s\", count: 10 }}\n"
+        ));
+        assert_eq!(
+            cfg.histogram,
+            Some(HistogramBins {
+                width: 30.0,
+                count: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_time_series() {
+        let cfg = parse(BASE);
+        assert_eq!(cfg.time_series, None);
+        let cfg = parse(&format!(
+            "{BASE}time_series: {{ interval: \"15m\", file: \"timeseries.csv\" }}\n"
+        ));
+        assert_eq!(
+            cfg.time_series,
+            Some(TimeBuckets {
+                interval: 900.0,
+                file: Some("timeseries.csv".to_string())
+            })
+        );
+    }
+
+    #[test]
+    fn test_costs() {
+        let cfg = parse(BASE);
+        assert_eq!(cfg.costs, None);
+        let cfg = parse(&format!(
+            "{BASE}costs: {{ window_hour: 18, revenue_per_order: 9.5, wait_penalty_per_minute: 0.5, wait_threshold: \"3m\" }}\n"
+        ));
+        assert_eq!(
+            cfg.costs,
+            Some(CostModel {
+                window_hour: 18.0,
+                revenue_per_order: 9.5,
+                lost_sale: 0.0,
+                wait_penalty_per_minute: 0.5,
+                wait_threshold: 180.0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_replications() {
+        let cfg = parse(BASE);
+        assert_eq!(cfg.replications, 1);
+        assert_eq!(cfg.replications_file, None);
+        let cfg = parse(&format!(
+            "{BASE}replications: 20\nreplications_file: \"summary.csv\"\n"
+        ));
+        assert_eq!(cfg.replications, 20);
+        assert_eq!(cfg.replications_file.as_deref(), Some("summary.csv"));
+    }
+
+    #[test]
+    fn test_sweep() {
+        let cfg = parse(&format!(
+            "{BASE}min_service_time: 10\nmax_service_time: 60\nsweep:\n  num_windows: [1, 2, 3]\n  avg_arrival_interval: [30, \"1min\"]\n  file: \"sweep.csv\"\n"
+        ));
+        let sweep = cfg.sweep.as_ref().unwrap();
+        assert_eq!(sweep.avg_arrival_interval, vec![30.0, 60.0]);
+        assert!(sweep.service_distribution.is_empty());
+
+        let points = sweep.design_points(&cfg.design_point().unwrap());
+        assert_eq!(points.len(), 6);
+        assert_eq!(
+            points[0].service,
+            Some(ServiceDistribution::Uniform {
+                min: 10.0,
+                max: 60.0
+            })
+        );
+    }
+
+    #[test]
+    fn test_optimize() {
+        let cfg = parse(&format!(
+            "{BASE}optimize:\n  max_windows: 6\n  sla:\n    max_wait: \"2min\"\n  blocks:\n    - {{ from: 0, to: \"1h\" }}\n    - {{ from: \"1h\", to: \"2h\" }}\n"
+        ));
+        let optimize = cfg.optimize.as_ref().unwrap();
+        assert_eq!(optimize.max_windows, 6);
+        assert_eq!(optimize.sla.percentile, 90.0);
+        assert_eq!(optimize.sla.max_wait, Some(120.0));
+        assert_eq!(optimize.sla.max_queue_length, None);
+        assert_eq!(optimize.blocks[1].to, 7200.0);
+    }
+
+    #[test]
+    fn test_missing_service_time_is_rejected() {
+        let cfg = parse(BASE);
+        assert!(cfg.service_distribution().is_err());
+    }
+
+    #[test]
+    fn test_stations() {
+        let cfg = parse(&format!(
+            "{BASE}stations:\n  - {{ name: order, lanes: 2, merge: alternate, service: {{ type: exponential, mean: 45 }}, buffer: 8 }}\n  - {{ name: pickup, servers: 2, service: {{ type: deterministic, value: \"1min\" }}, buffer: 1 }}\n"
+        ));
+        assert!(cfg.is_multi_stage());
+        assert_eq!(cfg.stations[0].lanes, 2);
+        assert_eq!(cfg.stations[0].servers, 1);
+        assert_eq!(cfg.stations[0].merge, MergePolicy::Alternate);
+        assert_eq!(cfg.stations[1].merge, MergePolicy::FirstFinished);
+        assert_eq!(cfg.stations[0].buffer, Some(8));
+        assert_eq!(cfg.stations[1].servers, 2);
+        assert_eq!(cfg.stations[1].buffer, Some(1));
+        assert!(cfg.validate().is_ok());
+        assert_eq!(
+            cfg.stations[1].service,
+            ServiceDistribution::Deterministic { value: 60.0 }
+        );
+    }
+
+    #[test]
+    fn test_classes() {
+        let cfg = parse(&format!(
+            "{BASE}classes:\n  - {{ name: mobile, share: 0.3, priority: 1, service: {{ type: exponential, mean: \"40s\" }} }}\n  - {{ name: regular, share: 0.7, service: {{ type: exponential, mean: \"2min\" }} }}\n"
+        ));
+        assert_eq!(cfg.classes.len(), 2);
+        assert_eq!(cfg.classes[0].priority, 1);
+        assert_eq!(cfg.classes[1].priority, 0);
+        assert_eq!(cfg.classes[1].share, 0.7);
+        assert_eq!(
+            cfg.classes[1].service,
+            Some(ServiceDistribution::Exponential { mean: 120.0 })
+        );
+    }
+
+    #[test]
+    fn test_queue_discipline() {
+        assert_eq!(parse(BASE).queue_discipline, QueueDiscipline::Fifo);
+        let cfg = parse(&format!("{BASE}queue_discipline: spt\n"));
+        assert_eq!(
+            cfg.queue_discipline,
+            QueueDiscipline::ShortestProcessingTime
+        );
+        let cfg = parse(&format!("{BASE}queue_discipline: lifo\n"));
+        assert_eq!(cfg.queue_discipline, QueueDiscipline::Lifo);
+    }
+
+    #[test]
+    fn test_window_lanes() {
+        assert_eq!(parse(BASE).window_lanes, None);
+        let cfg = parse(&format!(
+            "{BASE}window_lanes: {{ routing: least_work, jockey_threshold: 2 }}\n"
+        ));
+        let lanes = cfg.window_lanes.unwrap();
+        assert_eq!(lanes.routing, RoutingPolicy::LeastWork);
+        assert_eq!(lanes.jockey_threshold, Some(2));
+        let cfg = parse(&format!("{BASE}window_lanes: {{}}\n"));
+        assert_eq!(cfg.window_lanes, Some(WindowLanes::default()));
+    }
+
+    #[test]
+    fn test_windows() {
+        let cfg = parse(&format!(
+            "{BASE}windows:\n  - {{ name: main }}\n  - {{ name: trainee, speed: 0.7 }}\n  - {{ name: express, classes: [small] }}\n"
+        ));
+        assert_eq!(cfg.window_count(), 3);
+        assert_eq!(cfg.windows[0], Window::new("main"));
+        assert_eq!(cfg.windows[1].speed, 0.7);
+        assert_eq!(cfg.windows[2].classes, vec!["small".to_string()]);
+        assert_eq!(parse(BASE).window_count(), cfg.num_windows);
+    }
+
+    #[test]
+    fn test_staffing() {
+        let cfg = parse(&format!(
+            "{BASE}staffing:\n  - {{ from: 0, to: \"2h\", windows: 1 }}\n  - {{ from: \"20h\", to: \"22h30m\", windows: 0 }}\n"
+        ));
+        assert_eq!(
+            cfg.staffing[0],
+            Shift {
+                from: 0.0,
+                to: 7200.0,
+                windows: 1
+            }
+        );
+        assert_eq!(cfg.staffing[1].to, 81000.0);
+        assert!(parse(BASE).staffing.is_empty());
+    }
+
+    #[test]
+    fn test_breakdowns() {
+        let cfg = parse(&format!(
+            "{BASE}breakdowns: {{ time_between_failures: {{ type: exponential, mean: \"2h\" }}, repair_time: {{ type: deterministic, value: \"5min\" }}, interruption: restart }}\nwindows:\n  - {{ name: main }}\n  - {{ name: old, breakdowns: {{ time_between_failures: {{ type: exponential, mean: \"30min\" }}, repair_time: {{ type: deterministic, value: 60 }} }} }}\n"
+        ));
+        let breakdowns = cfg.breakdowns.unwrap();
+        assert_eq!(
+            breakdowns.time_between_failures,
+            ServiceDistribution::Exponential { mean: 7200.0 }
+        );
+        assert_eq!(breakdowns.interruption, Interruption::Restart);
+        assert_eq!(cfg.windows[0].breakdowns, None);
+        let own = cfg.windows[1].breakdowns.as_ref().unwrap();
+        assert_eq!(
+            own.repair_time,
+            ServiceDistribution::Deterministic { value: 60.0 }
+        );
+        assert_eq!(own.interruption, Interruption::Resume);
+    }
 }
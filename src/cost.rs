@@ -0,0 +1,163 @@
+use crate::duration::{deserialize_duration, format_duration};
+use serde::Deserialize;
+
+/// Prices turning the results of a run into money, so capacity options can be compared by
+/// profit (all amounts in the same currency)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CostModel {
+    /// Labor cost per staffed window and hour
+    #[serde(default)]
+    pub window_hour: f64,
+    /// Revenue per served order
+    #[serde(default)]
+    pub revenue_per_order: f64,
+    /// Revenue lost per customer who balked, reneged or was turned away
+    #[serde(default)]
+    pub lost_sale: f64,
+    /// Penalty per minute a served customer waited beyond `wait_threshold`
+    #[serde(default)]
+    pub wait_penalty_per_minute: f64,
+    /// Waiting time without penalty
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub wait_threshold: f64,
+}
+
+impl CostModel {
+    /// Checks that every price and the threshold are non-negative
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("window_hour", self.window_hour),
+            ("revenue_per_order", self.revenue_per_order),
+            ("lost_sale", self.lost_sale),
+            ("wait_penalty_per_minute", self.wait_penalty_per_minute),
+            ("wait_threshold", self.wait_threshold),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(format!("costs.{} must be non-negative", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the waiting time beyond the threshold that is charged for a wait of `wait_time`
+    pub fn excess_wait(&self, wait_time: f64) -> f64 {
+        (wait_time - self.wait_threshold).max(0.0)
+    }
+
+    /// Prices a run with `window_time` seconds of staffed window time, `served` orders,
+    /// `lost` customers and `excess_wait` seconds waited beyond the threshold
+    pub fn evaluate(
+        &self,
+        window_time: f64,
+        served: usize,
+        lost: usize,
+        excess_wait: f64,
+    ) -> CostSummary {
+        CostSummary {
+            window_hours: window_time / 3600.0,
+            served,
+            lost,
+            excess_wait_minutes: excess_wait / 60.0,
+            revenue: served as f64 * self.revenue_per_order,
+            labor: window_time / 3600.0 * self.window_hour,
+            lost_revenue: lost as f64 * self.lost_sale,
+            wait_penalty: excess_wait / 60.0 * self.wait_penalty_per_minute,
+        }
+    }
+}
+
+/// Revenue and costs of a run
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostSummary {
+    pub window_hours: f64,
+    pub served: usize,
+    pub lost: usize,
+    pub excess_wait_minutes: f64,
+    pub revenue: f64,
+    pub labor: f64,
+    pub lost_revenue: f64,
+    pub wait_penalty: f64,
+}
+
+impl CostSummary {
+    /// Returns the labor cost, the lost revenue and the wait penalty together
+    pub fn total_cost(&self) -> f64 {
+        self.labor + self.lost_revenue + self.wait_penalty
+    }
+
+    /// Returns the revenue minus the total cost
+    pub fn profit(&self) -> f64 {
+        self.revenue - self.total_cost()
+    }
+
+    /// Prints the revenue, every cost with the quantity it is charged for, and the profit
+    pub fn print(&self, costs: &CostModel) {
+        let line = |label: String, amount: f64| println!("{:<48} {:>12.2}", label, amount);
+        line(
+            format!(
+                "Revenue ({} orders × {:.2})",
+                self.served, costs.revenue_per_order
+            ),
+            self.revenue,
+        );
+        line(
+            format!(
+                "Labor ({:.2} window-hours × {:.2})",
+                self.window_hours, costs.window_hour
+            ),
+            -self.labor,
+        );
+        line(
+            format!(
+                "Lost revenue ({} customers × {:.2})",
+                self.lost, costs.lost_sale
+            ),
+            -self.lost_revenue,
+        );
+        line(
+            format!(
+                "Wait penalty ({:.1} min beyond {} × {:.2})",
+                self.excess_wait_minutes,
+                format_duration(costs.wait_threshold),
+                costs.wait_penalty_per_minute
+            ),
+            -self.wait_penalty,
+        );
+        line("Total cost".to_string(), -self.total_cost());
+        line("Profit".to_string(), self.profit());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let costs = CostModel {
+            window_hour: 18.0,
+            revenue_per_order: 9.5,
+            lost_sale: 9.5,
+            wait_penalty_per_minute: 0.5,
+            wait_threshold: 180.0,
+        };
+        assert_eq!(costs.excess_wait(120.0), 0.0);
+        assert_eq!(costs.excess_wait(300.0), 120.0);
+
+        // 2 windows for 10 hours, 400 served, 10 lost, 30 minutes beyond the threshold
+        let summary = costs.evaluate(72_000.0, 400, 10, 1800.0);
+        assert_eq!(summary.revenue, 3800.0);
+        assert_eq!(summary.labor, 360.0);
+        assert_eq!(summary.lost_revenue, 95.0);
+        assert_eq!(summary.wait_penalty, 15.0);
+        assert_eq!(summary.profit(), 3330.0);
+        assert!(
+            CostModel {
+                lost_sale: -1.0,
+                ..costs
+            }
+            .validate()
+            .is_err()
+        );
+    }
+}
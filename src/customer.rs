@@ -3,6 +3,40 @@
 pub struct Customer {
     pub arrival_time: f64,
     pub service_duration: f64,
+    /// Index of the customer's class (0 when no classes are configured)
+    pub class: usize,
+    /// Maximum time the customer waits in the queue before leaving (None waits indefinitely)
+    pub patience: Option<f64>,
+    /// Lane the customer is in when every window has its own lane (None with a shared queue)
+    pub window: Option<usize>,
+    /// Time the customer joined the lane (later than the arrival when blocked on the street)
+    pub queue_entry_time: Option<f64>,
     pub service_start_time: Option<f64>,
     pub service_end_time: Option<f64>,
+    /// How the visit ended (None while the customer has not arrived or is still in the system)
+    pub outcome: Option<Outcome>,
+}
+
+/// How a customer's visit ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Served,
+    /// Drove past without joining the queue
+    Balked,
+    /// Turned away because the lane was full
+    Rejected,
+    /// Left the queue before being served
+    Reneged,
+}
+
+impl Outcome {
+    /// Returns the name used in the customer CSV
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Served => "served",
+            Self::Balked => "balked",
+            Self::Rejected => "rejected",
+            Self::Reneged => "reneged",
+        }
+    }
 }
@@ -0,0 +1,424 @@
+use crate::duration::{deserialize_duration, deserialize_duration_list};
+use rand::Rng;
+use rand_distr::{Distribution, Exp, Gamma, LogNormal, Normal, Triangular};
+use serde::Deserialize;
+
+/// Probability distribution of the time needed to serve a customer
+///
+/// Selected in YAML with a `type` tag, e.g. `{ type: exponential, mean: "2min" }`.
+/// All time parameters accept the same formats as other durations in the config.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServiceDistribution {
+    /// Uniformly distributed between `min` and `max`
+    Uniform {
+        #[serde(deserialize_with = "deserialize_duration")]
+        min: f64,
+        #[serde(deserialize_with = "deserialize_duration")]
+        max: f64,
+    },
+    /// Exponentially distributed with the given mean (memoryless service)
+    Exponential {
+        #[serde(deserialize_with = "deserialize_duration")]
+        mean: f64,
+    },
+    /// Every customer takes exactly `value`
+    Deterministic {
+        #[serde(deserialize_with = "deserialize_duration")]
+        value: f64,
+    },
+    /// Normally distributed, truncated at zero (non-positive draws are rejected)
+    Normal {
+        #[serde(deserialize_with = "deserialize_duration")]
+        mean: f64,
+        #[serde(deserialize_with = "deserialize_duration")]
+        std_dev: f64,
+    },
+    /// Log-normally distributed with the given mean and standard deviation of the service time itself
+    LogNormal {
+        #[serde(deserialize_with = "deserialize_duration")]
+        mean: f64,
+        #[serde(deserialize_with = "deserialize_duration")]
+        std_dev: f64,
+    },
+    /// Gamma distributed with the given shape and mean
+    Gamma {
+        shape: f64,
+        #[serde(deserialize_with = "deserialize_duration")]
+        mean: f64,
+    },
+    /// Erlang-k distributed: the sum of `k` exponential phases with the given total mean
+    Erlang {
+        k: u32,
+        #[serde(deserialize_with = "deserialize_duration")]
+        mean: f64,
+    },
+    /// Triangular distribution between `min` and `max` peaking at `mode`
+    Triangular {
+        #[serde(deserialize_with = "deserialize_duration")]
+        min: f64,
+        #[serde(deserialize_with = "deserialize_duration")]
+        mode: f64,
+        #[serde(deserialize_with = "deserialize_duration")]
+        max: f64,
+    },
+    /// Resamples measured service times with equal probability
+    Empirical {
+        #[serde(deserialize_with = "deserialize_duration_list")]
+        samples: Vec<f64>,
+    },
+}
+
+impl ServiceDistribution {
+    /// Checks that the parameters describe a valid distribution of positive times
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Self::Uniform { min, max } => {
+                if min <= 0.0 {
+                    return Err("uniform: min must be positive".into());
+                }
+                if max < min {
+                    return Err("uniform: max must be >= min".into());
+                }
+            }
+            Self::Exponential { mean } => {
+                if mean <= 0.0 {
+                    return Err("exponential: mean must be positive".into());
+                }
+            }
+            Self::Deterministic { value } => {
+                if value <= 0.0 {
+                    return Err("deterministic: value must be positive".into());
+                }
+            }
+            Self::Normal { mean, std_dev } | Self::LogNormal { mean, std_dev } => {
+                if mean <= 0.0 {
+                    return Err(format!("{}: mean must be positive", self.name()));
+                }
+                if std_dev < 0.0 {
+                    return Err(format!("{}: std_dev must be non-negative", self.name()));
+                }
+            }
+            Self::Gamma { shape, mean } => {
+                if shape <= 0.0 {
+                    return Err("gamma: shape must be positive".into());
+                }
+                if mean <= 0.0 {
+                    return Err("gamma: mean must be positive".into());
+                }
+            }
+            Self::Erlang { k, mean } => {
+                if k == 0 {
+                    return Err("erlang: k must be at least 1".into());
+                }
+                if mean <= 0.0 {
+                    return Err("erlang: mean must be positive".into());
+                }
+            }
+            Self::Triangular { min, mode, max } => {
+                if min <= 0.0 {
+                    return Err("triangular: min must be positive".into());
+                }
+                if !(min <= mode && mode <= max) {
+                    return Err("triangular: expected min <= mode <= max".into());
+                }
+            }
+            Self::Empirical { ref samples } => {
+                if samples.is_empty() {
+                    return Err("empirical: at least one sample is required".into());
+                }
+                if samples.iter().any(|&s| s <= 0.0) {
+                    return Err("empirical: all samples must be positive".into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the name used as the `type` tag in the configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Uniform { .. } => "uniform",
+            Self::Exponential { .. } => "exponential",
+            Self::Deterministic { .. } => "deterministic",
+            Self::Normal { .. } => "normal",
+            Self::LogNormal { .. } => "log_normal",
+            Self::Gamma { .. } => "gamma",
+            Self::Erlang { .. } => "erlang",
+            Self::Triangular { .. } => "triangular",
+            Self::Empirical { .. } => "empirical",
+        }
+    }
+
+    /// Returns the mean service time (of the untruncated distribution for `normal`)
+    pub fn mean(&self) -> f64 {
+        match *self {
+            Self::Uniform { min, max } => (min + max) / 2.0,
+            Self::Exponential { mean }
+            | Self::Normal { mean, .. }
+            | Self::LogNormal { mean, .. }
+            | Self::Gamma { mean, .. }
+            | Self::Erlang { mean, .. } => mean,
+            Self::Deterministic { value } => value,
+            Self::Triangular { min, mode, max } => (min + mode + max) / 3.0,
+            Self::Empirical { ref samples } => samples.iter().sum::<f64>() / samples.len() as f64,
+        }
+    }
+
+    /// Returns the variance of the service time (of the untruncated distribution for `normal`)
+    pub fn variance(&self) -> f64 {
+        match *self {
+            Self::Uniform { min, max } => (max - min).powi(2) / 12.0,
+            Self::Exponential { mean } => mean * mean,
+            Self::Deterministic { .. } => 0.0,
+            Self::Normal { std_dev, .. } | Self::LogNormal { std_dev, .. } => std_dev * std_dev,
+            Self::Gamma { shape, mean } => mean * mean / shape,
+            Self::Erlang { k, mean } => mean * mean / f64::from(k),
+            Self::Triangular { min, mode, max } => {
+                (min * min + mode * mode + max * max - min * mode - min * max - mode * max) / 18.0
+            }
+            Self::Empirical { ref samples } => {
+                let mean = self.mean();
+                samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / samples.len() as f64
+            }
+        }
+    }
+
+    /// Draws a strictly positive service time
+    ///
+    /// Draws that are not positive (e.g. the negative tail of the normal distribution)
+    /// are rejected and redrawn.
+    ///
+    /// The parameters must be valid (see [`ServiceDistribution::validate`]).
+    ///
+    /// # Panics
+    /// May panic if the parameters are invalid
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        loop {
+            let value = self.draw(rng);
+            if value > 0.0 {
+                return value;
+            }
+        }
+    }
+
+    /// Draws a single raw value, which may be non-positive for unbounded distributions
+    fn draw<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match *self {
+            Self::Uniform { min, max } => rng.random_range(min..=max),
+            Self::Exponential { mean } => Exp::new(1.0 / mean).unwrap().sample(rng),
+            Self::Deterministic { value } => value,
+            Self::Normal { mean, std_dev } => Normal::new(mean, std_dev).unwrap().sample(rng),
+            Self::LogNormal { mean, std_dev } => {
+                let sigma2 = (1.0 + (std_dev * std_dev) / (mean * mean)).ln();
+                let mu = mean.ln() - sigma2 / 2.0;
+                LogNormal::new(mu, sigma2.sqrt()).unwrap().sample(rng)
+            }
+            Self::Gamma { shape, mean } => Gamma::new(shape, mean / shape).unwrap().sample(rng),
+            Self::Erlang { k, mean } => {
+                let k = f64::from(k);
+                Gamma::new(k, mean / k).unwrap().sample(rng)
+            }
+            Self::Triangular { min, mode, max } => {
+                if min == max {
+                    min
+                } else {
+                    Triangular::new(min, max, mode).unwrap().sample(rng)
+                }
+            }
+            Self::Empirical { ref samples } => samples[rng.random_range(0..samples.len())],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn sample_mean(dist: &ServiceDistribution, n: usize) -> f64 {
+        let mut rng = StdRng::seed_from_u64(1);
+        (0..n).map(|_| dist.sample(&mut rng)).sum::<f64>() / n as f64
+    }
+
+    #[test]
+    fn test_deterministic_is_constant() {
+        let dist = ServiceDistribution::Deterministic { value: 42.0 };
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..10 {
+            assert_eq!(dist.sample(&mut rng), 42.0);
+        }
+    }
+
+    #[test]
+    fn test_sample_means() {
+        let cases = [
+            (
+                ServiceDistribution::Uniform {
+                    min: 10.0,
+                    max: 50.0,
+                },
+                30.0,
+            ),
+            (ServiceDistribution::Exponential { mean: 60.0 }, 60.0),
+            (
+                ServiceDistribution::LogNormal {
+                    mean: 60.0,
+                    std_dev: 30.0,
+                },
+                60.0,
+            ),
+            (
+                ServiceDistribution::Gamma {
+                    shape: 2.5,
+                    mean: 60.0,
+                },
+                60.0,
+            ),
+            (ServiceDistribution::Erlang { k: 3, mean: 60.0 }, 60.0),
+            (
+                ServiceDistribution::Triangular {
+                    min: 30.0,
+                    mode: 60.0,
+                    max: 90.0,
+                },
+                60.0,
+            ),
+        ];
+        for (dist, expected) in cases {
+            assert_eq!(dist.mean(), expected);
+            let mean = sample_mean(&dist, 20_000);
+            assert!(
+                (mean - expected).abs() / expected < 0.05,
+                "{}: expected mean {}, got {:.2}",
+                dist.name(),
+                expected,
+                mean
+            );
+        }
+    }
+
+    #[test]
+    fn test_variances() {
+        let cases = [
+            (
+                ServiceDistribution::Uniform {
+                    min: 10.0,
+                    max: 50.0,
+                },
+                1600.0 / 12.0,
+            ),
+            (ServiceDistribution::Exponential { mean: 60.0 }, 3600.0),
+            (
+                ServiceDistribution::Gamma {
+                    shape: 2.5,
+                    mean: 60.0,
+                },
+                1440.0,
+            ),
+            (ServiceDistribution::Erlang { k: 3, mean: 60.0 }, 1200.0),
+            (
+                ServiceDistribution::Triangular {
+                    min: 30.0,
+                    mode: 60.0,
+                    max: 90.0,
+                },
+                150.0,
+            ),
+            (
+                ServiceDistribution::Empirical {
+                    samples: vec![20.0, 40.0],
+                },
+                100.0,
+            ),
+        ];
+        let mut rng = StdRng::seed_from_u64(1);
+        for (dist, expected) in cases {
+            assert!((dist.variance() - expected).abs() < 1e-9);
+            let samples: Vec<f64> = (0..50_000).map(|_| dist.sample(&mut rng)).collect();
+            let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+            let variance =
+                samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+            assert!(
+                (variance - expected).abs() / expected < 0.05,
+                "{}: expected variance {}, got {:.2}",
+                dist.name(),
+                expected,
+                variance
+            );
+        }
+        assert_eq!(
+            ServiceDistribution::Deterministic { value: 30.0 }.variance(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_normal_is_truncated_at_zero() {
+        let dist = ServiceDistribution::Normal {
+            mean: 5.0,
+            std_dev: 10.0,
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..1000 {
+            assert!(dist.sample(&mut rng) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_empirical_only_returns_samples() {
+        let samples = vec![20.0, 45.0, 90.0];
+        let dist = ServiceDistribution::Empirical {
+            samples: samples.clone(),
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..100 {
+            assert!(samples.contains(&dist.sample(&mut rng)));
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_parameters() {
+        assert!(
+            ServiceDistribution::Uniform {
+                min: 10.0,
+                max: 5.0
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            ServiceDistribution::Exponential { mean: 0.0 }
+                .validate()
+                .is_err()
+        );
+        assert!(
+            ServiceDistribution::Erlang { k: 0, mean: 10.0 }
+                .validate()
+                .is_err()
+        );
+        assert!(
+            ServiceDistribution::Triangular {
+                min: 10.0,
+                mode: 40.0,
+                max: 30.0
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            ServiceDistribution::Empirical { samples: vec![] }
+                .validate()
+                .is_err()
+        );
+        assert!(
+            ServiceDistribution::Gamma {
+                shape: 2.0,
+                mean: 30.0
+            }
+            .validate()
+            .is_ok()
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use serde::de::{self, Visitor};
-use serde::Deserializer;
+use serde::{Deserialize, Deserializer};
 use std::fmt;
 use std::time::Duration;
 
@@ -175,3 +175,27 @@ where
 
     deserializer.deserialize_any(DurationVisitor)
 }
+
+/// Helper that deserializes a single duration, used to build list and optional variants
+#[derive(Deserialize)]
+struct DurationValue(#[serde(deserialize_with = "deserialize_duration")] f64);
+
+/// Custom deserializer for a list of durations in any format accepted by [`deserialize_duration`]
+pub fn deserialize_duration_list<'de, D>(deserializer: D) -> Result<Vec<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let values = Vec::<DurationValue>::deserialize(deserializer)?;
+    Ok(values.into_iter().map(|v| v.0).collect())
+}
+
+/// Custom deserializer for an optional duration in any format accepted by [`deserialize_duration`]
+///
+/// Use together with `#[serde(default)]` so that a missing field becomes `None`
+pub fn deserialize_optional_duration<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<DurationValue>::deserialize(deserializer)?;
+    Ok(value.map(|v| v.0))
+}
@@ -3,6 +3,15 @@ pub enum EventType {
     Arrival,
     ServiceStart,
     ServiceEnd,
+    Balk,
+    Renege,
+    Blocked,
+    Reject,
+    Jockey,
+    WindowOpen,
+    WindowClose,
+    Breakdown,
+    Repair,
 }
 
 impl std::fmt::Display for EventType {
@@ -11,6 +20,15 @@ impl std::fmt::Display for EventType {
             EventType::Arrival => write!(f, "Arrival"),
             EventType::ServiceStart => write!(f, "ServiceStart"),
             EventType::ServiceEnd => write!(f, "ServiceEnd"),
+            EventType::Balk => write!(f, "Balk"),
+            EventType::Renege => write!(f, "Renege"),
+            EventType::Blocked => write!(f, "Blocked"),
+            EventType::Reject => write!(f, "Reject"),
+            EventType::Jockey => write!(f, "Jockey"),
+            EventType::WindowOpen => write!(f, "WindowOpen"),
+            EventType::WindowClose => write!(f, "WindowClose"),
+            EventType::Breakdown => write!(f, "Breakdown"),
+            EventType::Repair => write!(f, "Repair"),
         }
     }
 }
@@ -0,0 +1,275 @@
+use crate::duration::{deserialize_duration, format_duration};
+use serde::Deserialize;
+
+/// Relative accuracy of the quantiles estimated by [`QuantileSketch`]
+const RELATIVE_ACCURACY: f64 = 0.01;
+
+/// Smallest value told apart from 0 (1 millisecond)
+const MIN_VALUE: f64 = 0.001;
+
+/// Percentiles listed in the report
+pub const REPORTED_PERCENTILES: [f64; 4] = [50.0, 90.0, 95.0, 99.0];
+
+/// Longest bar of a printed histogram, in characters
+const BAR_WIDTH: usize = 40;
+
+/// Streaming quantile estimator with a fixed relative error
+///
+/// Values are counted in logarithmically spaced buckets (as in HDR histograms), so the
+/// memory grows with the logarithm of the range of the values rather than with their
+/// number. Estimated quantiles are within 1% of a value of the requested rank.
+#[derive(Debug, Clone)]
+pub struct QuantileSketch {
+    /// Values below [`MIN_VALUE`]
+    zeros: u64,
+    /// Bucket `i` counts the values in `(MIN_VALUE * γ^(i-1), MIN_VALUE * γ^i]`
+    buckets: Vec<u64>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+/// Ratio between the bounds of a bucket
+fn gamma() -> f64 {
+    (1.0 + RELATIVE_ACCURACY) / (1.0 - RELATIVE_ACCURACY)
+}
+
+impl QuantileSketch {
+    /// Creates an empty sketch
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            zeros: 0,
+            buckets: Vec::new(),
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Records a non-negative value
+    pub fn record(&mut self, value: f64) {
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if value < MIN_VALUE {
+            self.zeros += 1;
+            return;
+        }
+        let index = ((value / MIN_VALUE).ln() / gamma().ln()).ceil().max(0.0) as usize;
+        if self.buckets.len() <= index {
+            self.buckets.resize(index + 1, 0);
+        }
+        self.buckets[index] += 1;
+    }
+
+    /// Returns the number of recorded values
+    #[allow(dead_code)] // Library API, used by the integration tests
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the estimated `p`-th percentile (nearest rank), 0 if nothing was recorded
+    ///
+    /// The smallest and the largest value are exact.
+    pub fn quantile(&self, p: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let rank = ((p / 100.0 * self.count as f64).ceil() as u64).clamp(1, self.count);
+        if rank == self.count {
+            return self.max;
+        }
+        if rank == 1 || rank <= self.zeros {
+            return self.min;
+        }
+        let mut seen = self.zeros;
+        for (index, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                // Midpoint of the bucket in relative terms
+                let estimate = MIN_VALUE * 2.0 * gamma().powi(index as i32) / (gamma() + 1.0);
+                return estimate.clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Returns the reported percentiles as text, e.g. `P50 12s, P90 1m 5s, ...`
+    pub fn summary(&self) -> String {
+        REPORTED_PERCENTILES
+            .iter()
+            .map(|&p| format!("P{} {}", p, format_duration(self.quantile(p))))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Equal-width bins of the histograms in the report
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct HistogramBins {
+    /// Width of each bin
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub width: f64,
+    /// Number of bins; longer times are counted in a final open-ended bin
+    pub count: usize,
+}
+
+impl HistogramBins {
+    /// Checks that the width is positive and that there is at least one bin
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width <= 0.0 {
+            return Err("histogram.width must be positive".into());
+        }
+        if self.count == 0 {
+            return Err("histogram.count must be greater than 0".into());
+        }
+        Ok(())
+    }
+}
+
+/// Counts of values in equal-width bins, with a final bin for the values beyond them
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bins: HistogramBins,
+    /// One count per bin, followed by the count of the open-ended bin
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    /// Creates an empty histogram with the given bins
+    #[must_use]
+    pub fn new(bins: HistogramBins) -> Self {
+        Self {
+            bins,
+            counts: vec![0; bins.count + 1],
+        }
+    }
+
+    /// Returns the bins of the histogram
+    pub fn bins(&self) -> HistogramBins {
+        self.bins
+    }
+
+    /// Returns the count of every bin, the open-ended bin last
+    #[allow(dead_code)] // Library API, used by the integration tests
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Records a non-negative value
+    pub fn record(&mut self, value: f64) {
+        let index = ((value / self.bins.width) as usize).min(self.bins.count);
+        self.counts[index] += 1;
+    }
+
+    /// Prints one line per bin with its count, its share and a bar
+    pub fn print(&self) {
+        let total: u64 = self.counts.iter().sum();
+        if total == 0 {
+            return;
+        }
+        let largest = self.counts.iter().copied().max().unwrap_or(0);
+        for (index, &count) in self.counts.iter().enumerate() {
+            let from = index as f64 * self.bins.width;
+            let label = if index < self.bins.count {
+                format!(
+                    "{} - {}",
+                    format_duration(from),
+                    format_duration(from + self.bins.width)
+                )
+            } else {
+                format!("{}+", format_duration(from))
+            };
+            let bar = (count as f64 / largest as f64 * BAR_WIDTH as f64).round() as usize;
+            println!(
+                "{:<20} {:>7} {:>7.2}% {}",
+                label,
+                count,
+                count as f64 / total as f64 * 100.0,
+                "#".repeat(bar)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantiles_within_relative_accuracy() {
+        let mut sketch = QuantileSketch::new();
+        let values: Vec<f64> = (1..=10_000).map(|i| i as f64 * 0.37).collect();
+        for &value in &values {
+            sketch.record(value);
+        }
+
+        assert_eq!(sketch.count(), 10_000);
+        for p in [1.0, 25.0, 50.0, 90.0, 95.0, 99.0] {
+            let exact = values[(p / 100.0 * 10_000.0) as usize - 1];
+            let estimate = sketch.quantile(p);
+            assert!(
+                (estimate - exact).abs() <= RELATIVE_ACCURACY * exact,
+                "P{}: {} vs {}",
+                p,
+                estimate,
+                exact
+            );
+        }
+        assert_eq!(sketch.quantile(100.0), 3700.0);
+        assert_eq!(sketch.quantile(0.0), 0.37);
+    }
+
+    #[test]
+    fn test_quantiles_of_zero_waits() {
+        let mut sketch = QuantileSketch::new();
+        assert_eq!(sketch.quantile(50.0), 0.0);
+        for _ in 0..8 {
+            sketch.record(0.0);
+        }
+        sketch.record(30.0);
+        sketch.record(60.0);
+
+        assert_eq!(sketch.quantile(50.0), 0.0);
+        assert_eq!(sketch.quantile(80.0), 0.0);
+        assert!((sketch.quantile(90.0) - 30.0).abs() <= 0.3);
+        assert_eq!(sketch.quantile(95.0), 60.0);
+    }
+
+    #[test]
+    fn test_histogram_bins() {
+        let bins = HistogramBins {
+            width: 30.0,
+            count: 3,
+        };
+        let mut histogram = Histogram::new(bins);
+        for value in [0.0, 29.9, 30.0, 75.0, 90.0, 600.0] {
+            histogram.record(value);
+        }
+
+        assert_eq!(histogram.counts(), &[2, 1, 1, 2]);
+        assert!(
+            HistogramBins {
+                width: 0.0,
+                count: 3
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            HistogramBins {
+                width: 30.0,
+                count: 0
+            }
+            .validate()
+            .is_err()
+        );
+    }
+}
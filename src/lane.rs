@@ -0,0 +1,13 @@
+use serde::Deserialize;
+
+/// What happens to an arriving car when the drive-through lane is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LaneOverflow {
+    /// The car is turned away and lost
+    #[default]
+    Reject,
+    /// The car waits on the street, blocking the entrance for cars behind it,
+    /// until a space in the lane frees up
+    Block,
+}
@@ -1,14 +1,35 @@
 // Library interface for drive-through simulation
 // This exposes modules for testing and potential library usage
 
+pub mod analytics;
+pub mod arrival;
+pub mod balking;
+pub mod breakdown;
+pub mod class;
 pub mod clock;
+pub mod closing;
 pub mod config;
+pub mod cost;
 pub mod customer;
+pub mod distribution;
 pub mod duration;
 pub mod event;
+pub mod histogram;
 pub mod history;
+pub mod lane;
+pub mod optimizer;
 pub mod output;
+pub mod queue;
+pub mod replication;
+pub mod routing;
+pub mod seed;
 pub mod simulation;
+pub mod staffing;
 pub mod state;
 pub mod statistics;
+pub mod sweep;
+pub mod tandem;
+pub mod timeseries;
+pub mod warmup;
+pub mod window;
 
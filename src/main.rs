@@ -1,18 +1,48 @@
+mod analytics;
+mod arrival;
+mod balking;
+mod breakdown;
+mod class;
 mod clock;
+mod closing;
 mod config;
+mod cost;
 mod customer;
+mod distribution;
 mod duration;
 mod event;
+mod histogram;
 mod history;
+mod lane;
+mod optimizer;
 mod output;
+mod queue;
+mod replication;
+mod routing;
+mod seed;
 mod simulation;
+mod staffing;
 mod state;
 mod statistics;
+mod sweep;
+mod tandem;
+mod timeseries;
+mod warmup;
+mod window;
 
+use analytics::{OfferedLoad, print_offered_load};
+use arrival::ArrivalProfile;
 use clap::Parser;
 use config::Config;
+use config::random::RandomSimConfig;
+use duration::format_duration;
+use optimizer::print_optimization_report;
+use replication::Replications;
 use simulation::Simulation;
 use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use sweep::{DesignPoint, SweepResults};
+use tandem::TandemSimulation;
 
 /// Drive-Through Simulation System
 #[derive(Parser, Debug)]
@@ -22,6 +52,16 @@ struct Args {
     /// Path to the configuration file
     #[arg(short, long, default_value = "config.yaml")]
     config: String,
+
+    /// Seed for both simulations (overrides `seed` of `fixed_simulation` and `random_simulation`
+    /// in the config file)
+    #[arg(short, long)]
+    seed: Option<u64>,
+
+    /// Number of independent replications of the random simulation (overrides
+    /// `random_simulation.replications` in the config file)
+    #[arg(short, long)]
+    replications: Option<NonZeroUsize>,
 }
 
 #[tokio::main]
@@ -35,7 +75,9 @@ async fn main() {
             eprintln!(
                 "Please ensure the config file exists and at least one simulation is enabled."
             );
-            eprintln!("\nUsage: drive-through-simulation [--config <FILE>]");
+            eprintln!(
+                "\nUsage: drive-through-simulation [--config <FILE>] [--seed <SEED>] [--replications <N>]"
+            );
             eprintln!("  Default config file: config.yaml");
             eprintln!("\nExample:");
             eprintln!("  drive-through-simulation --config my-config.yaml");
@@ -57,14 +99,76 @@ async fn main() {
 
     if config.fixed_simulation.enabled {
         println!("=== Drive-Through Simulation (Fixed Data from Config) ===");
+        print_offered_load(&config.fixed_simulation.offered_load());
+        let seed = args
+            .seed
+            .or(config.fixed_simulation.seed)
+            .unwrap_or_else(rand::random);
+        println!("Random seed: {}", seed);
         let _ = io::stdout().flush();
-        let mut sim_fixed = Simulation::new(config.fixed_simulation.num_windows);
+        let mut sim_fixed = Simulation::with_seed(config.fixed_simulation.window_count(), seed);
+        if let Some(rule) = &config.fixed_simulation.balking {
+            sim_fixed.set_balking(rule.clone());
+        }
+        if let Some(dist) = &config.fixed_simulation.patience {
+            sim_fixed.set_patience(dist.clone());
+        }
+        if let Some(capacity) = config.fixed_simulation.lane_capacity {
+            sim_fixed.set_lane_capacity(capacity, config.fixed_simulation.lane_overflow);
+        }
+        if !config.fixed_simulation.classes.is_empty() {
+            sim_fixed.set_classes(config.fixed_simulation.classes.clone());
+        }
+        sim_fixed.set_queue_discipline(config.fixed_simulation.queue_discipline);
+        if let Some(lanes) = &config.fixed_simulation.window_lanes {
+            sim_fixed.set_window_lanes(lanes.clone());
+        }
+        if !config.fixed_simulation.windows.is_empty() {
+            sim_fixed.set_windows(config.fixed_simulation.windows.clone());
+        }
+        if !config.fixed_simulation.staffing.is_empty() {
+            sim_fixed.set_staffing(config.fixed_simulation.staffing.clone());
+        }
+        if let Some(breakdowns) = &config.fixed_simulation.breakdowns {
+            sim_fixed.set_breakdowns(breakdowns.clone());
+        }
         for cust in &config.fixed_simulation.customers {
-            sim_fixed.add_customer(cust.arrival, cust.service);
+            let class = config
+                .fixed_simulation
+                .class_index(cust)
+                .expect("customer classes are validated when loading the config");
+            sim_fixed.add_class_customer(cust.arrival, cust.service, class, cust.patience);
         }
 
-        sim_fixed.run(None, Some(&config.fixed_simulation.history_file)).await;
+        sim_fixed.set_closing(config.fixed_simulation.closing);
+        if let Some(bins) = config.fixed_simulation.histogram {
+            sim_fixed.set_histogram(bins);
+        }
+        if let Some(filename) = &config.fixed_simulation.customer_file {
+            sim_fixed.set_customer_file(filename);
+        }
+        if let Some(buckets) = &config.fixed_simulation.time_series {
+            sim_fixed.set_time_series(buckets.interval);
+        }
+        if let Some(costs) = &config.fixed_simulation.costs {
+            sim_fixed.set_costs(costs.clone());
+        }
+        sim_fixed
+            .run(
+                config.fixed_simulation.closing_time,
+                Some(&config.fixed_simulation.history_file),
+            )
+            .await;
         sim_fixed.print_statistics();
+        if let Some(filename) = config
+            .fixed_simulation
+            .time_series
+            .as_ref()
+            .and_then(|b| b.file.as_ref())
+            && let Err(e) = sim_fixed.write_time_series(filename)
+        {
+            eprintln!("Warning: Failed to write {}: {}", filename, e);
+        }
 
         if config.random_simulation.enabled {
             println!("\n");
@@ -74,17 +178,237 @@ async fn main() {
     if config.random_simulation.enabled {
         println!("=== Drive-Through Simulation (Random Data from Config) ===");
         let _ = io::stdout().flush();
-        let mut sim_random = Simulation::new(config.random_simulation.num_windows);
         let r = &config.random_simulation;
-        sim_random.generate_random_customers(
-            r.max_simulation_time,
-            r.avg_arrival_interval,
-            r.min_service_time,
-            r.max_service_time,
-        );
-        sim_random.run(Some(r.max_simulation_time), Some(&r.history_file)).await;
-        sim_random.print_statistics();
+        let seed = args.seed.or(r.seed).unwrap_or_else(rand::random);
+        println!("Random seed: {}", seed);
+        let _ = io::stdout().flush();
+        if r.is_multi_stage() {
+            if args.replications.is_some() {
+                eprintln!("Warning: replications are not supported with stations, running once");
+            }
+            let names: Vec<&str> = r.stations.iter().map(|s| s.name.as_str()).collect();
+            println!("Stations: {}", names.join(" -> "));
+            print_offered_load(&offered_load(r));
+            let arrivals = r
+                .arrival_profile()
+                .expect("arrival profile is validated when loading the config");
+            let mut sim_tandem = TandemSimulation::with_seed(r.stations.clone(), seed);
+            sim_tandem.generate_customers(r.max_simulation_time, &arrivals);
+            sim_tandem
+                .run(Some(r.max_simulation_time), Some(&r.history_file))
+                .await;
+            sim_tandem.print_statistics();
+        } else {
+            describe_random(r);
+            let design_point = r
+                .design_point()
+                .expect("service distribution is validated when loading the config");
+            let replications = args.replications.map_or(r.replications, NonZeroUsize::get);
+            if let Some(optimizer) = &r.optimize {
+                println!(
+                    "Staffing optimization: {}, up to {} windows, {} replications each (seeds {} to {})",
+                    optimizer.sla.label(),
+                    optimizer.max_windows,
+                    replications,
+                    seed,
+                    seed.wrapping_add(replications as u64 - 1)
+                );
+                if replications < 2 {
+                    eprintln!(
+                        "Warning: a single replication gives no confidence interval, the service level is checked on one run"
+                    );
+                }
+                let results = optimizer
+                    .run(
+                        replications,
+                        seed,
+                        Some(r.max_simulation_time),
+                        |num_windows, shifts, seed| {
+                            let point = DesignPoint {
+                                num_windows,
+                                ..design_point.clone()
+                            };
+                            let mut sim = random_simulation(r, &point, seed);
+                            if !shifts.is_empty() {
+                                sim.set_staffing(shifts);
+                            }
+                            sim
+                        },
+                    )
+                    .await;
+                print_optimization_report(&results, optimizer.max_windows);
+            } else if let Some(sweep) = &r.sweep {
+                let points = sweep.design_points(&design_point);
+                println!(
+                    "Sweep: {} design points, {} replications each (seeds {} to {})",
+                    points.len(),
+                    replications,
+                    seed,
+                    seed.wrapping_add(replications as u64 - 1)
+                );
+                let results = SweepResults::run(
+                    points,
+                    replications,
+                    seed,
+                    Some(r.max_simulation_time),
+                    |point, seed| random_simulation(r, point, seed),
+                )
+                .await;
+                results.print_summary();
+                if let Err(e) = results.write_csv(&sweep.file) {
+                    eprintln!("Warning: Failed to write {}: {}", sweep.file, e);
+                }
+            } else if replications > 1 {
+                println!(
+                    "Replications: {} (seeds {} to {})",
+                    replications,
+                    seed,
+                    seed.wrapping_add(replications as u64 - 1)
+                );
+                let results =
+                    Replications::run(replications, seed, Some(r.max_simulation_time), |seed| {
+                        random_simulation(r, &design_point, seed)
+                    })
+                    .await;
+                results.print_summary();
+                if let Some(filename) = &r.replications_file
+                    && let Err(e) = results.write_csv(filename)
+                {
+                    eprintln!("Warning: Failed to write {}: {}", filename, e);
+                }
+            } else {
+                let mut sim_random = random_simulation(r, &design_point, seed);
+                if let Some(filename) = &r.customer_file {
+                    sim_random.set_customer_file(filename);
+                }
+                if let Some(buckets) = &r.time_series {
+                    sim_random.set_time_series(buckets.interval);
+                }
+                sim_random
+                    .run(Some(r.max_simulation_time), Some(&r.history_file))
+                    .await;
+                sim_random.print_statistics();
+                if let Some(filename) = r.time_series.as_ref().and_then(|b| b.file.as_ref())
+                    && let Err(e) = sim_random.write_time_series(filename)
+                {
+                    eprintln!("Warning: Failed to write {}: {}", filename, e);
+                }
+            }
+        }
     }
 
     println!("\nSimulation(s) completed.");
 }
+
+/// Prints the settings of the random simulation
+fn describe_random(r: &RandomSimConfig) {
+    println!("Queue discipline: {}", r.queue_discipline.name());
+    if let Some(lanes) = &r.window_lanes {
+        println!(
+            "Window lanes: routing {}, {}",
+            lanes.routing.name(),
+            match lanes.jockey_threshold {
+                Some(threshold) => format!("jockeying at a difference of {} cars", threshold),
+                None => "no jockeying".to_string(),
+            }
+        );
+    }
+    if r.classes.is_empty() {
+        let service = r
+            .service_distribution()
+            .expect("service distribution is validated when loading the config");
+        println!("Service time distribution: {}", service.name());
+    } else {
+        let names: Vec<&str> = r.classes.iter().map(|c| c.name.as_str()).collect();
+        println!("Customer classes: {}", names.join(", "));
+    }
+    if !r.windows.is_empty() {
+        let names: Vec<&str> = r.windows.iter().map(|w| w.name.as_str()).collect();
+        println!("Windows: {}", names.join(", "));
+    }
+    if !r.staffing.is_empty() {
+        let shifts: Vec<String> = r
+            .staffing
+            .iter()
+            .map(|s| format!("{} ({} open)", s.label(), s.windows))
+            .collect();
+        println!("Staffing: {}", shifts.join(", "));
+    }
+    if let Some(breakdowns) = &r.breakdowns {
+        println!(
+            "Breakdowns: time between failures {}, repair time {}, interrupted service {}s",
+            breakdowns.time_between_failures.name(),
+            breakdowns.repair_time.name(),
+            breakdowns.interruption.name()
+        );
+    }
+    println!("Closing: {}", r.closing.name());
+    if let Some(warmup) = r.warmup {
+        println!(
+            "Warm-up: first {} deleted from the statistics",
+            format_duration(warmup)
+        );
+    }
+    print_offered_load(&offered_load(r));
+    let _ = io::stdout().flush();
+}
+
+/// Returns the offered load of the random simulation
+fn offered_load(r: &RandomSimConfig) -> Vec<OfferedLoad> {
+    r.offered_load()
+        .expect("arrival profile, staffing and service are validated when loading the config")
+}
+
+/// Builds the single-stage random simulation of `point` for `seed`, with its customers generated
+fn random_simulation(r: &RandomSimConfig, point: &DesignPoint, seed: u64) -> Simulation {
+    let arrivals = ArrivalProfile::new(point.avg_arrival_interval, r.arrival_profile.clone())
+        .expect("arrival profile is validated when loading the config");
+    let mut sim = Simulation::with_seed(point.num_windows, seed);
+    if let Some(rule) = &r.balking {
+        sim.set_balking(rule.clone());
+    }
+    if let Some(dist) = &r.patience {
+        sim.set_patience(dist.clone());
+    }
+    if let Some(capacity) = r.lane_capacity {
+        sim.set_lane_capacity(capacity, r.lane_overflow);
+    }
+    sim.set_queue_discipline(r.queue_discipline);
+    if let Some(lanes) = &r.window_lanes {
+        sim.set_window_lanes(lanes.clone());
+    }
+    match &point.service {
+        Some(service) => sim.generate_customers(r.max_simulation_time, &arrivals, service),
+        None => {
+            sim.set_classes(r.classes.clone());
+            sim.generate_class_customers(r.max_simulation_time, &arrivals);
+        }
+    }
+    if !r.windows.is_empty() {
+        sim.set_windows(r.windows.clone());
+    }
+    if !r.staffing.is_empty() {
+        sim.set_staffing(r.staffing.clone());
+    }
+    if let Some(breakdowns) = &r.breakdowns {
+        sim.set_breakdowns(breakdowns.clone());
+    }
+    sim.set_closing(r.closing);
+    if let Some(warmup) = r.warmup {
+        sim.set_warmup(warmup);
+    }
+    if r.detect_warmup {
+        // About one queue length observation per customer
+        sim.set_warmup_detection(r.avg_arrival_interval);
+    }
+    if let Some(bins) = r.histogram {
+        sim.set_histogram(bins);
+    }
+    if let Some(model) = r.queueing_model(point) {
+        sim.set_queueing_model(model);
+    }
+    if let Some(costs) = &r.costs {
+        sim.set_costs(costs.clone());
+    }
+    sim
+}
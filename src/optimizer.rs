@@ -0,0 +1,431 @@
+use crate::duration::{deserialize_duration, deserialize_optional_duration, format_duration};
+use crate::replication::MetricSummary;
+use crate::simulation::Simulation;
+use crate::staffing::{Shift, StaffingSchedule};
+use crate::statistics::{SegmentStatistics, percentile};
+use serde::Deserialize;
+use std::io::{self, Write};
+
+fn default_percentile() -> f64 {
+    90.0
+}
+
+/// Service level agreement the staffing has to meet
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ServiceLevel {
+    /// Percentage of customers whose wait must stay under `max_wait`
+    #[serde(default = "default_percentile")]
+    pub percentile: f64,
+    /// Maximum wait of the `percentile` percent of customers waiting the least
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub max_wait: Option<f64>,
+    /// Maximum time-weighted average queue length
+    #[serde(default)]
+    pub max_queue_length: Option<f64>,
+}
+
+impl ServiceLevel {
+    /// Checks that at least one constraint is set and that the limits are valid
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_wait.is_none() && self.max_queue_length.is_none() {
+            return Err("sla needs max_wait or max_queue_length".into());
+        }
+        if self.percentile <= 0.0 || self.percentile > 100.0 {
+            return Err("sla.percentile must be in (0, 100]".into());
+        }
+        if self.max_wait.is_some_and(|w| w < 0.0) || self.max_queue_length.is_some_and(|q| q < 0.0)
+        {
+            return Err("sla limits must be non-negative".into());
+        }
+        Ok(())
+    }
+
+    /// Returns a description of the constraints for messages
+    pub fn label(&self) -> String {
+        let mut constraints = Vec::new();
+        if let Some(max_wait) = self.max_wait {
+            constraints.push(format!(
+                "{}% of waits under {}",
+                self.percentile,
+                format_duration(max_wait)
+            ));
+        }
+        if let Some(max_queue_length) = self.max_queue_length {
+            constraints.push(format!("average queue length at most {}", max_queue_length));
+        }
+        constraints.join(", ")
+    }
+}
+
+/// Period of the day staffed with its own number of windows
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StaffingBlock {
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub from: f64,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub to: f64,
+}
+
+/// Search for the fewest windows meeting a service level
+///
+/// Without blocks, the number of windows of the whole run is searched. With blocks, the
+/// number of windows of each block is searched in chronological order, with the earlier
+/// blocks at the number found for them and the later ones at `max_windows`; the service
+/// level then applies to the customers arriving within the block.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StaffingOptimizer {
+    /// Largest number of windows tried
+    pub max_windows: usize,
+    pub sla: ServiceLevel,
+    #[serde(default)]
+    pub blocks: Vec<StaffingBlock>,
+}
+
+/// Outcome of the replications of one number of windows
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub windows: usize,
+    /// The waiting time percentile of the service level, over the replications
+    pub wait: Option<MetricSummary>,
+    /// The average queue length, over the replications
+    pub queue_length: Option<MetricSummary>,
+    /// Whether the upper bounds of the confidence intervals meet the service level
+    pub feasible: bool,
+}
+
+/// Candidates tried for the whole run or one block, in increasing number of windows
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockResult {
+    pub from: f64,
+    pub to: f64,
+    pub candidates: Vec<Candidate>,
+}
+
+impl BlockResult {
+    /// Returns the fewest windows meeting the service level (None if none did)
+    pub fn windows(&self) -> Option<usize> {
+        self.candidates
+            .iter()
+            .find(|c| c.feasible)
+            .map(|c| c.windows)
+    }
+}
+
+impl StaffingOptimizer {
+    /// Checks the service level and that the blocks are valid and do not overlap
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_windows == 0 {
+            return Err("max_windows must be greater than 0".into());
+        }
+        self.sla.validate()?;
+        StaffingSchedule::new(self.max_windows, self.shifts(&[]))
+            .map(|_| ())
+            .map_err(|e| format!("blocks: {}", e))
+    }
+
+    /// Returns the blocks as shifts, with the given numbers of windows for the first blocks
+    /// and `max_windows` for the rest
+    fn shifts(&self, windows: &[usize]) -> Vec<Shift> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| Shift {
+                from: block.from,
+                to: block.to,
+                windows: windows.get(i).copied().unwrap_or(self.max_windows),
+            })
+            .collect()
+    }
+
+    /// Searches the fewest windows meeting the service level, with `replications`
+    /// replications of every candidate run until `max_time`
+    ///
+    /// `build` creates the simulation with the given number of windows and staffing shifts
+    /// for a seed, with its customers generated. Returns the result of every block (one
+    /// for the whole run without blocks).
+    pub async fn run(
+        &self,
+        replications: usize,
+        seed: u64,
+        max_time: Option<f64>,
+        mut build: impl FnMut(usize, Vec<Shift>, u64) -> Simulation,
+    ) -> Vec<BlockResult> {
+        if self.blocks.is_empty() {
+            let mut result = BlockResult {
+                from: 0.0,
+                to: f64::INFINITY,
+                candidates: Vec::new(),
+            };
+            for windows in 1..=self.max_windows {
+                let candidate = self
+                    .evaluate(
+                        windows,
+                        (0.0, f64::INFINITY),
+                        replications,
+                        seed,
+                        max_time,
+                        |seed| build(windows, Vec::new(), seed),
+                    )
+                    .await;
+                let feasible = candidate.feasible;
+                result.candidates.push(candidate);
+                if feasible {
+                    break;
+                }
+            }
+            return vec![result];
+        }
+
+        let mut chosen = Vec::new();
+        let mut results = Vec::new();
+        for block in &self.blocks {
+            println!(
+                "Block {} - {}:",
+                format_duration(block.from),
+                format_duration(block.to)
+            );
+            let mut result = BlockResult {
+                from: block.from,
+                to: block.to,
+                candidates: Vec::new(),
+            };
+            for windows in 1..=self.max_windows {
+                let mut staffed = chosen.clone();
+                staffed.push(windows);
+                let shifts = self.shifts(&staffed);
+                let candidate = self
+                    .evaluate(
+                        windows,
+                        (block.from, block.to),
+                        replications,
+                        seed,
+                        max_time,
+                        |seed| build(self.max_windows, shifts.clone(), seed),
+                    )
+                    .await;
+                let feasible = candidate.feasible;
+                result.candidates.push(candidate);
+                if feasible {
+                    break;
+                }
+            }
+            // A block no number of windows satisfies stays fully staffed
+            chosen.push(result.windows().unwrap_or(self.max_windows));
+            results.push(result);
+        }
+        results
+    }
+
+    /// Runs the replications of one candidate and checks the service level for the
+    /// customers arriving in `period`
+    async fn evaluate(
+        &self,
+        windows: usize,
+        period: (f64, f64),
+        replications: usize,
+        seed: u64,
+        max_time: Option<f64>,
+        mut build: impl FnMut(u64) -> Simulation,
+    ) -> Candidate {
+        let (from, to) = period;
+        let mut waits = Vec::with_capacity(replications);
+        let mut queue_lengths = Vec::with_capacity(replications);
+        for i in 0..replications {
+            let mut sim = build(seed.wrapping_add(i as u64));
+            sim.set_quiet(true);
+            sim.state
+                .lock()
+                .unwrap()
+                .stats
+                .segments
+                .push(SegmentStatistics::new("sla".into(), from, to));
+            sim.run(max_time, None).await;
+
+            // Customers arriving during a warm-up period are left out
+            let (start, queue_length) = {
+                let state = sim.state.lock().unwrap();
+                let segment = state
+                    .stats
+                    .segments
+                    .last()
+                    .expect("the period was added above");
+                let start = from.max(state.stats.observation_start);
+                let observed = to.min(state.current_time) - start;
+                let queue_length = if observed > 0.0 {
+                    segment.queue_length_integral / observed
+                } else {
+                    0.0
+                };
+                (start, queue_length)
+            };
+            // Nobody arriving in the period is no evidence the service level is met
+            let period_waits = sim.censored_waiting_times(start, to);
+            waits.push(if period_waits.is_empty() {
+                f64::INFINITY
+            } else {
+                percentile(&period_waits, self.sla.percentile)
+            });
+            queue_lengths.push(queue_length);
+        }
+
+        let wait = self
+            .sla
+            .max_wait
+            .map(|_| MetricSummary::new("Waiting time percentile (s)", &waits));
+        let queue_length = self
+            .sla
+            .max_queue_length
+            .map(|_| MetricSummary::new("Average queue length", &queue_lengths));
+        let meets = |summary: &Option<MetricSummary>, limit: Option<f64>| match (summary, limit) {
+            (Some(summary), Some(limit)) => {
+                summary.max.is_finite() && summary.mean + summary.half_width <= limit
+            }
+            _ => true,
+        };
+        let feasible =
+            meets(&wait, self.sla.max_wait) && meets(&queue_length, self.sla.max_queue_length);
+
+        let mut line = format!("  {} windows:", windows);
+        if let Some(wait) = &wait {
+            if wait.max.is_finite() {
+                line.push_str(&format!(
+                    " P{} wait {} ± {}",
+                    self.sla.percentile,
+                    format_duration(wait.mean),
+                    format_duration(wait.half_width)
+                ));
+            } else {
+                // A replication had no served customer in the percentile
+                line.push_str(&format!(" P{} wait unbounded", self.sla.percentile));
+            }
+        }
+        if let Some(queue_length) = &queue_length {
+            line.push_str(&format!(
+                " average queue {:.2} ± {:.2}",
+                queue_length.mean, queue_length.half_width
+            ));
+        }
+        println!("{} ({})", line, if feasible { "met" } else { "not met" });
+        let _ = io::stdout().flush();
+
+        Candidate {
+            windows,
+            wait,
+            queue_length,
+            feasible,
+        }
+    }
+}
+
+/// Prints the cheapest feasible configuration found by [`StaffingOptimizer::run`]
+pub fn print_optimization_report(results: &[BlockResult], max_windows: usize) {
+    println!("\nStaffing Optimization:");
+    println!("-----------------------------------------------");
+    if let [result] = results
+        && result.to.is_infinite()
+    {
+        match result.windows() {
+            Some(windows) => println!("Cheapest feasible configuration: {} windows", windows),
+            None => println!(
+                "No feasible configuration with up to {} windows",
+                max_windows
+            ),
+        }
+        return;
+    }
+
+    let mut window_hours = 0.0;
+    for result in results {
+        let windows = result.windows();
+        println!(
+            "{} - {}: {}",
+            format_duration(result.from),
+            format_duration(result.to),
+            match windows {
+                Some(windows) => format!("{} windows", windows),
+                None => format!("not feasible with up to {} windows", max_windows),
+            }
+        );
+        window_hours += windows.unwrap_or(max_windows) as f64 * (result.to - result.from) / 3600.0;
+    }
+    if results.iter().all(|r| r.windows().is_some()) {
+        println!(
+            "Cheapest feasible schedule: {:.2} window-hours",
+            window_hours
+        );
+    } else {
+        println!(
+            "No feasible schedule; {:.2} window-hours with the infeasible blocks fully staffed",
+            window_hours
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sla() -> ServiceLevel {
+        ServiceLevel {
+            percentile: 90.0,
+            max_wait: Some(120.0),
+            max_queue_length: None,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut optimizer = StaffingOptimizer {
+            max_windows: 4,
+            sla: sla(),
+            blocks: vec![
+                StaffingBlock {
+                    from: 0.0,
+                    to: 3600.0,
+                },
+                StaffingBlock {
+                    from: 3600.0,
+                    to: 7200.0,
+                },
+            ],
+        };
+        assert!(optimizer.validate().is_ok());
+        optimizer.blocks[1].from = 1800.0;
+        assert!(optimizer.validate().is_err());
+        optimizer.blocks.clear();
+        optimizer.sla.max_wait = None;
+        assert!(optimizer.validate().is_err());
+    }
+
+    #[test]
+    fn test_shifts() {
+        let optimizer = StaffingOptimizer {
+            max_windows: 4,
+            sla: sla(),
+            blocks: vec![
+                StaffingBlock {
+                    from: 0.0,
+                    to: 3600.0,
+                },
+                StaffingBlock {
+                    from: 3600.0,
+                    to: 7200.0,
+                },
+            ],
+        };
+        let shifts = optimizer.shifts(&[2]);
+        assert_eq!(shifts[0].windows, 2);
+        assert_eq!(shifts[1].windows, 4);
+        assert_eq!(shifts[1].from, 3600.0);
+    }
+
+    #[test]
+    fn test_sla_label() {
+        let mut sla = sla();
+        sla.max_queue_length = Some(3.0);
+        assert_eq!(
+            sla.label(),
+            "90% of waits under 2m, average queue length at most 3"
+        );
+    }
+}
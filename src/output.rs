@@ -1,13 +1,69 @@
+use crate::duration::format_duration;
 use crate::event::EventType;
+use std::fs::File;
+use std::io::{self, Write};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
 /// Message sent to the output thread for ordered printing
 #[derive(Debug, Clone)]
 pub struct OutputMessage {
     pub time: f64,
     pub event: EventType,
-    pub cust_id: usize,
+    /// Customer the event belongs to (None for window events such as `WindowOpen`)
+    pub cust_id: Option<usize>,
     pub queue_len: usize,
     pub busy_servers: usize,
+    /// Number of windows open (all windows unless staffing follows a schedule)
     pub num_windows: usize,
+    /// Station index in a multi-stage simulation (None for the single-stage model)
+    pub station: Option<usize>,
+    /// Window lane of the customer when every window has its own lane
+    pub window: Option<usize>,
+    /// Customer class index (None when no classes are configured)
+    pub class: Option<usize>,
 }
 
+/// Rule printed above and below the event log
+const LOG_RULE: &str =
+    "-------------------------------------------------------------------------------------------";
+
+/// Prints the title and the column header of the event log
+pub fn print_log_header(title: &str, header: &str) {
+    println!("{}", title);
+    println!("{}", header.trim_end());
+    println!("{}", LOG_RULE);
+    let _ = io::stdout().flush();
+}
+
+/// Prints the end of the event log and the time the simulation finished
+pub fn print_log_footer(end_time: f64) {
+    println!("{}", LOG_RULE);
+    println!("Simulation finished at T={}", format_duration(end_time));
+}
+
+/// Spawns the output thread, which prints the events in the order they were sent, one
+/// line per event formatted by `format`
+///
+/// The thread finishes once every sender is dropped.
+pub fn spawn_event_printer(
+    mut output_rx: mpsc::UnboundedReceiver<OutputMessage>,
+    format: impl Fn(&OutputMessage) -> String + Send + 'static,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(msg) = output_rx.recv().await {
+            println!("{}", format(&msg).trim_end());
+            let _ = io::stdout().flush();
+        }
+    })
+}
+
+/// Creates a CSV file starting with the seed of the run (if any) and the column `header`
+pub fn create_csv(filename: &str, seed: Option<u64>, header: &str) -> io::Result<File> {
+    let mut file = File::create(filename)?;
+    if let Some(seed) = seed {
+        writeln!(file, "# Seed: {}", seed)?;
+    }
+    writeln!(file, "{}", header)?;
+    Ok(file)
+}
@@ -0,0 +1,259 @@
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+/// Order in which waiting customers are taken by the servers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueDiscipline {
+    /// First come, first served, ignoring class priorities
+    #[default]
+    Fifo,
+    /// Last come, first served
+    Lifo,
+    /// Shortest processing time first (e.g. a fast lane for small orders); ties in arrival order
+    #[serde(rename = "spt")]
+    ShortestProcessingTime,
+    /// Higher class priority first, in arrival order within a priority
+    /// (same as FIFO without customer classes)
+    Priority,
+    /// A waiting customer chosen uniformly at random
+    Random,
+}
+
+impl QueueDiscipline {
+    /// Returns the name used in the configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Fifo => "fifo",
+            Self::Lifo => "lifo",
+            Self::ShortestProcessingTime => "spt",
+            Self::Priority => "priority",
+            Self::Random => "random",
+        }
+    }
+}
+
+/// A waiting customer with the attributes the disciplines order by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueueEntry {
+    pub cust_id: usize,
+    pub priority: u32,
+    pub service_duration: f64,
+}
+
+/// Customers waiting for a server, taken out in the order given by a [`QueueDiscipline`]
+///
+/// Entries are kept sorted so that the next customer is at the front (at the back for LIFO);
+/// any waiting customer can be removed, e.g. when they renege.
+#[derive(Debug, Clone, Default)]
+pub struct WaitingQueue {
+    discipline: QueueDiscipline,
+    entries: VecDeque<QueueEntry>,
+}
+
+impl WaitingQueue {
+    /// Creates an empty queue with the given discipline
+    #[must_use]
+    pub fn new(discipline: QueueDiscipline) -> Self {
+        Self {
+            discipline,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn discipline(&self) -> QueueDiscipline {
+        self.discipline
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds a waiting customer
+    pub fn push(&mut self, entry: QueueEntry) {
+        let pos = match self.discipline {
+            QueueDiscipline::Fifo | QueueDiscipline::Lifo | QueueDiscipline::Random => {
+                self.entries.len()
+            }
+            QueueDiscipline::ShortestProcessingTime => self
+                .entries
+                .iter()
+                .rposition(|e| e.service_duration <= entry.service_duration)
+                .map_or(0, |i| i + 1),
+            QueueDiscipline::Priority => self
+                .entries
+                .iter()
+                .rposition(|e| e.priority >= entry.priority)
+                .map_or(0, |i| i + 1),
+        };
+        self.entries.insert(pos, entry);
+    }
+
+    /// Takes the next customer to serve
+    ///
+    /// `rng` is only used by [`QueueDiscipline::Random`].
+    pub fn pop<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<QueueEntry> {
+        self.pop_where(rng, |_| true)
+    }
+
+    /// Takes the next customer to serve among those accepted by `eligible`
+    /// (e.g. the customers a window can serve)
+    pub fn pop_where<R, F>(&mut self, rng: &mut R, eligible: F) -> Option<QueueEntry>
+    where
+        R: Rng + ?Sized,
+        F: Fn(&QueueEntry) -> bool,
+    {
+        let index = match self.discipline {
+            QueueDiscipline::Lifo => self.entries.iter().rposition(eligible)?,
+            QueueDiscipline::Random => {
+                let candidates: Vec<usize> = (0..self.entries.len())
+                    .filter(|&i| eligible(&self.entries[i]))
+                    .collect();
+                if candidates.is_empty() {
+                    return None;
+                }
+                candidates[rng.random_range(0..candidates.len())]
+            }
+            _ => self.entries.iter().position(eligible)?,
+        };
+        self.entries.remove(index)
+    }
+
+    /// Takes the customer who would be served last (e.g. to move them to a shorter lane)
+    ///
+    /// For [`QueueDiscipline::Random`] this is the most recently added customer.
+    pub fn pop_last(&mut self) -> Option<QueueEntry> {
+        match self.discipline {
+            QueueDiscipline::Lifo => self.entries.pop_front(),
+            _ => self.entries.pop_back(),
+        }
+    }
+
+    /// Returns the customer [`WaitingQueue::pop_last`] would take
+    pub fn last(&self) -> Option<&QueueEntry> {
+        match self.discipline {
+            QueueDiscipline::Lifo => self.entries.front(),
+            _ => self.entries.back(),
+        }
+    }
+
+    /// Removes a waiting customer, returning false if they are not in the queue
+    pub fn remove(&mut self, cust_id: usize) -> bool {
+        match self.entries.iter().position(|e| e.cust_id == cust_id) {
+            Some(pos) => {
+                self.entries.remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Iterates over the waiting customers
+    pub fn iter(&self) -> impl Iterator<Item = &QueueEntry> {
+        self.entries.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn drain(discipline: QueueDiscipline) -> Vec<usize> {
+        // (priority, service duration) of customers 0..5 in arrival order
+        let customers = [(0, 50.0), (1, 90.0), (0, 10.0), (1, 30.0), (0, 30.0)];
+        let mut queue = WaitingQueue::new(discipline);
+        for (cust_id, &(priority, service_duration)) in customers.iter().enumerate() {
+            queue.push(QueueEntry {
+                cust_id,
+                priority,
+                service_duration,
+            });
+        }
+        let mut rng = StdRng::seed_from_u64(1);
+        std::iter::from_fn(|| queue.pop(&mut rng).map(|e| e.cust_id)).collect()
+    }
+
+    #[test]
+    fn test_disciplines_order() {
+        assert_eq!(drain(QueueDiscipline::Fifo), vec![0, 1, 2, 3, 4]);
+        assert_eq!(drain(QueueDiscipline::Lifo), vec![4, 3, 2, 1, 0]);
+        assert_eq!(
+            drain(QueueDiscipline::ShortestProcessingTime),
+            vec![2, 3, 4, 0, 1]
+        );
+        assert_eq!(drain(QueueDiscipline::Priority), vec![1, 3, 0, 2, 4]);
+    }
+
+    #[test]
+    fn test_random_serves_everyone_once() {
+        let mut order = drain(QueueDiscipline::Random);
+        assert_ne!(order, vec![0, 1, 2, 3, 4]);
+        order.sort_unstable();
+        assert_eq!(order, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut queue = WaitingQueue::new(QueueDiscipline::Fifo);
+        for cust_id in 0..3 {
+            queue.push(QueueEntry {
+                cust_id,
+                priority: 0,
+                service_duration: 1.0,
+            });
+        }
+        assert!(queue.remove(1));
+        assert!(!queue.remove(1));
+        let ids: Vec<usize> = queue.iter().map(|e| e.cust_id).collect();
+        assert_eq!(ids, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_pop_where_skips_ineligible() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for discipline in [
+            QueueDiscipline::Fifo,
+            QueueDiscipline::Lifo,
+            QueueDiscipline::Random,
+        ] {
+            let mut queue = WaitingQueue::new(discipline);
+            for cust_id in 0..4 {
+                queue.push(QueueEntry {
+                    cust_id,
+                    priority: 0,
+                    service_duration: cust_id as f64,
+                });
+            }
+            let small = |e: &QueueEntry| e.service_duration < 2.0;
+            let first = queue.pop_where(&mut rng, small).unwrap().cust_id;
+            let second = queue.pop_where(&mut rng, small).unwrap().cust_id;
+            assert_eq!(first + second, 1, "{:?}", discipline);
+            assert_eq!(queue.pop_where(&mut rng, small), None);
+            assert_eq!(queue.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_pop_last() {
+        let mut fifo = WaitingQueue::new(QueueDiscipline::Fifo);
+        let mut lifo = WaitingQueue::new(QueueDiscipline::Lifo);
+        for cust_id in 0..3 {
+            let entry = QueueEntry {
+                cust_id,
+                priority: 0,
+                service_duration: 1.0,
+            };
+            fifo.push(entry);
+            lifo.push(entry);
+        }
+        assert_eq!(fifo.pop_last().map(|e| e.cust_id), Some(2));
+        assert_eq!(lifo.pop_last().map(|e| e.cust_id), Some(0));
+    }
+}
@@ -0,0 +1,234 @@
+use crate::duration::format_duration;
+use crate::simulation::Simulation;
+use std::fs::File;
+use std::io::{self, Write};
+
+/// Two-sided 97.5% quantiles of the Student t distribution for 1 to 30 degrees of freedom
+const T_QUANTILES_975: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// Returns the Student t quantile for a 95% confidence interval with `df` degrees of freedom
+///
+/// Uses a table up to 30 degrees of freedom and the Cornish-Fisher expansion around the
+/// normal quantile beyond.
+///
+/// # Panics
+/// Panics if `df` is 0
+pub fn t_quantile_95(df: usize) -> f64 {
+    assert!(df > 0, "Degrees of freedom must be positive");
+    if let Some(&t) = T_QUANTILES_975.get(df - 1) {
+        return t;
+    }
+    let z: f64 = 1.959_964;
+    let v = df as f64;
+    z + (z.powi(3) + z) / (4.0 * v)
+        + (5.0 * z.powi(5) + 16.0 * z.powi(3) + 3.0 * z) / (96.0 * v * v)
+}
+
+/// Mean, spread and 95% confidence interval of one metric over independent replications
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSummary {
+    pub name: &'static str,
+    pub mean: f64,
+    /// Sample standard deviation (0 for a single replication)
+    pub std_dev: f64,
+    /// Half-width of the Student t confidence interval of the mean (0 for a single replication)
+    pub half_width: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl MetricSummary {
+    /// Summarizes the values of the metric `name` in each replication
+    ///
+    /// # Panics
+    /// Panics if `values` is empty
+    #[must_use]
+    pub fn new(name: &'static str, values: &[f64]) -> Self {
+        assert!(!values.is_empty(), "At least one value is required");
+        let n = values.len();
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let (std_dev, half_width) = if n > 1 {
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+            let std_dev = variance.sqrt();
+            (std_dev, t_quantile_95(n - 1) * std_dev / (n as f64).sqrt())
+        } else {
+            (0.0, 0.0)
+        };
+        Self {
+            name,
+            mean,
+            std_dev,
+            half_width,
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+/// Results of independent replications of a simulation
+#[derive(Debug, Clone)]
+pub struct Replications {
+    /// Seed of each replication
+    pub seeds: Vec<u64>,
+    /// Metrics of each replication (see [`Simulation::metrics`])
+    pub runs: Vec<Vec<(&'static str, f64)>>,
+}
+
+impl Replications {
+    /// Runs `count` replications with the seeds `seed`, `seed + 1`, ...
+    ///
+    /// `build` creates the simulation for a seed, with its customers generated; every
+    /// replication runs quietly until `max_time` without a history file, so the first one
+    /// reproduces a single run with `seed`. The random streams of a simulation are derived
+    /// from its seed (see [`Stream::seed`](crate::seed::Stream::seed)), so consecutive seeds share no stream.
+    ///
+    /// # Panics
+    /// Panics if `count` is 0
+    pub async fn run(
+        count: usize,
+        seed: u64,
+        max_time: Option<f64>,
+        mut build: impl FnMut(u64) -> Simulation,
+    ) -> Self {
+        assert!(count > 0, "At least one replication is required");
+        let mut replications = Self {
+            seeds: Vec::with_capacity(count),
+            runs: Vec::with_capacity(count),
+        };
+        for i in 0..count {
+            let seed = seed.wrapping_add(i as u64);
+            let mut sim = build(seed);
+            sim.set_quiet(true);
+            sim.run(max_time, None).await;
+            let metrics = sim.metrics();
+            println!(
+                "Replication {} (seed {}): {:.0} customers completed, average wait {}",
+                i + 1,
+                seed,
+                value(&metrics, "Customers completed"),
+                format_duration(value(&metrics, "Average waiting time (s)"))
+            );
+            let _ = io::stdout().flush();
+            replications.seeds.push(seed);
+            replications.runs.push(metrics);
+        }
+        replications
+    }
+
+    /// Summarizes every metric over the replications
+    pub fn summary(&self) -> Vec<MetricSummary> {
+        let Some(first) = self.runs.first() else {
+            return Vec::new();
+        };
+        first
+            .iter()
+            .map(|&(name, _)| {
+                let values: Vec<f64> = self.runs.iter().map(|run| value(run, name)).collect();
+                MetricSummary::new(name, &values)
+            })
+            .collect()
+    }
+
+    /// Prints the summary table, leaving out metrics that are 0 in every replication
+    pub fn print_summary(&self) {
+        println!(
+            "\nReplication Summary ({} replications, 95% confidence intervals):",
+            self.runs.len()
+        );
+        println!("-----------------------------------------------");
+        println!(
+            "{:<30} {:>10} {:>10} {:>23} {:>10} {:>10}",
+            "Metric", "Mean", "Std dev", "95% CI", "Min", "Max"
+        );
+        for metric in self.summary() {
+            if metric.min == 0.0 && metric.max == 0.0 {
+                continue;
+            }
+            println!(
+                "{:<30} {:>10.2} {:>10.2} {:>23} {:>10.2} {:>10.2}",
+                metric.name,
+                metric.mean,
+                metric.std_dev,
+                format!(
+                    "[{:.2}, {:.2}]",
+                    metric.mean - metric.half_width,
+                    metric.mean + metric.half_width
+                ),
+                metric.min,
+                metric.max
+            );
+        }
+    }
+
+    /// Writes the summary of every metric and its value in each replication to a CSV file
+    pub fn write_csv(&self, filename: &str) -> io::Result<()> {
+        let mut file = File::create(filename)?;
+        let runs: Vec<String> = self
+            .seeds
+            .iter()
+            .map(|seed| format!("seed_{}", seed))
+            .collect();
+        writeln!(
+            file,
+            "metric,mean,std_dev,ci_low,ci_high,min,max,{}",
+            runs.join(",")
+        )?;
+        for metric in self.summary() {
+            let values: Vec<String> = self
+                .runs
+                .iter()
+                .map(|run| value(run, metric.name).to_string())
+                .collect();
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{},{}",
+                metric.name,
+                metric.mean,
+                metric.std_dev,
+                metric.mean - metric.half_width,
+                metric.mean + metric.half_width,
+                metric.min,
+                metric.max,
+                values.join(",")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the value of the metric `name` (0 if missing)
+fn value(metrics: &[(&'static str, f64)], name: &str) -> f64 {
+    metrics
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map_or(0.0, |&(_, value)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_t_quantile() {
+        assert_eq!(t_quantile_95(1), 12.706);
+        assert_eq!(t_quantile_95(30), 2.042);
+        assert!((t_quantile_95(40) - 2.021).abs() < 0.001);
+        assert!((t_quantile_95(120) - 1.980).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_metric_summary() {
+        let summary = MetricSummary::new("wait", &[2.0, 4.0, 6.0, 8.0]);
+        assert_eq!(summary.mean, 5.0);
+        assert!((summary.std_dev - (20.0_f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert!((summary.half_width - 3.182 * summary.std_dev / 2.0).abs() < 1e-12);
+        assert_eq!((summary.min, summary.max), (2.0, 8.0));
+
+        let single = MetricSummary::new("wait", &[3.0]);
+        assert_eq!((single.std_dev, single.half_width), (0.0, 0.0));
+    }
+}
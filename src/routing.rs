@@ -0,0 +1,162 @@
+use rand::Rng;
+use serde::Deserialize;
+
+/// How arriving cars choose a lane when every window has its own lane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingPolicy {
+    /// The lane with the fewest cars (waiting or at the window); ties go to the first lane
+    #[default]
+    ShortestQueue,
+    /// Lanes take turns
+    RoundRobin,
+    /// A lane chosen uniformly at random
+    Random,
+    /// The lane with the least service time left (waiting orders plus the order at the window)
+    LeastWork,
+}
+
+/// What an arriving car knows about a lane when choosing one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LaneLoad {
+    /// Cars waiting in the lane or at its window
+    pub cars: usize,
+    /// Service time left for all those cars
+    pub work: f64,
+    /// Whether the car can join the lane: it has room and its window serves the car
+    pub open: bool,
+}
+
+impl RoutingPolicy {
+    /// Returns the name used in the configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ShortestQueue => "shortest_queue",
+            Self::RoundRobin => "round_robin",
+            Self::Random => "random",
+            Self::LeastWork => "least_work",
+        }
+    }
+
+    /// Picks the lane an arriving car joins, or None if no lane is open
+    ///
+    /// `next_lane` is the lane whose turn it is for [`RoutingPolicy::RoundRobin`];
+    /// `rng` is only used by [`RoutingPolicy::Random`].
+    pub fn select<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        lanes: &[LaneLoad],
+        next_lane: usize,
+    ) -> Option<usize> {
+        let open: Vec<usize> = (0..lanes.len()).filter(|&k| lanes[k].open).collect();
+        if open.is_empty() {
+            return None;
+        }
+        let lane = match self {
+            Self::ShortestQueue => *open.iter().min_by_key(|&&k| lanes[k].cars).unwrap(),
+            Self::RoundRobin => (0..lanes.len())
+                .map(|offset| (next_lane + offset) % lanes.len())
+                .find(|&k| lanes[k].open)
+                .unwrap(),
+            Self::Random => open[rng.random_range(0..open.len())],
+            Self::LeastWork => *open
+                .iter()
+                .min_by(|&&a, &&b| {
+                    lanes[a]
+                        .work
+                        .total_cmp(&lanes[b].work)
+                        .then(lanes[a].cars.cmp(&lanes[b].cars))
+                })
+                .unwrap(),
+        };
+        Some(lane)
+    }
+}
+
+/// A separate lane in front of every window instead of one shared queue
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct WindowLanes {
+    /// How arriving cars choose a lane
+    #[serde(default)]
+    pub routing: RoutingPolicy,
+    /// A waiting car moves to an adjacent lane (jockeys) once that lane has at least this many
+    /// fewer cars; no jockeying when omitted
+    #[serde(default)]
+    pub jockey_threshold: Option<usize>,
+}
+
+impl WindowLanes {
+    /// Checks that the lane parameters are consistent
+    pub fn validate(&self) -> Result<(), String> {
+        // With a difference of 1, a car moving over would leave the lanes just as unbalanced
+        // the other way round and jump back and forth
+        if self.jockey_threshold.is_some_and(|threshold| threshold < 2) {
+            return Err("window_lanes: jockey_threshold must be at least 2".into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn lane(cars: usize, work: f64, open: bool) -> LaneLoad {
+        LaneLoad { cars, work, open }
+    }
+
+    #[test]
+    fn test_select() {
+        let lanes = [
+            lane(3, 90.0, true),
+            lane(2, 200.0, true),
+            lane(0, 0.0, false),
+            lane(2, 60.0, true),
+        ];
+        let mut rng = StdRng::seed_from_u64(3);
+
+        assert_eq!(
+            RoutingPolicy::ShortestQueue.select(&mut rng, &lanes, 0),
+            Some(1)
+        );
+        assert_eq!(
+            RoutingPolicy::LeastWork.select(&mut rng, &lanes, 0),
+            Some(3)
+        );
+        assert_eq!(
+            RoutingPolicy::RoundRobin.select(&mut rng, &lanes, 1),
+            Some(1)
+        );
+        assert_eq!(
+            RoutingPolicy::RoundRobin.select(&mut rng, &lanes, 2),
+            Some(3)
+        );
+        for _ in 0..20 {
+            let lane = RoutingPolicy::Random.select(&mut rng, &lanes, 0).unwrap();
+            assert_ne!(lane, 2);
+        }
+    }
+
+    #[test]
+    fn test_select_without_room() {
+        let lanes = [lane(4, 10.0, false), lane(4, 10.0, false)];
+        let mut rng = StdRng::seed_from_u64(3);
+        assert_eq!(
+            RoutingPolicy::ShortestQueue.select(&mut rng, &lanes, 0),
+            None
+        );
+        assert_eq!(RoutingPolicy::RoundRobin.select(&mut rng, &lanes, 1), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut lanes = WindowLanes::default();
+        assert!(lanes.validate().is_ok());
+        lanes.jockey_threshold = Some(2);
+        assert!(lanes.validate().is_ok());
+        lanes.jockey_threshold = Some(1);
+        assert!(lanes.validate().is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+/// Independent random number stream of a seeded simulation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    /// Arrival times and service times of the generated customers
+    Customers,
+    /// Random decisions during the run (probabilistic balking, drawn patience, breakdowns)
+    Decisions,
+}
+
+impl Stream {
+    /// Returns the seed of this stream for the simulation seed `seed`
+    ///
+    /// The seed is mixed with SplitMix64, so no stream of one seed coincides with a stream
+    /// of a nearby seed (such as the seed of the next replication).
+    #[must_use]
+    pub fn seed(self, seed: u64) -> u64 {
+        splitmix64(splitmix64(seed) ^ self as u64)
+    }
+
+    /// Returns a generator of this stream for the simulation seed `seed`
+    #[must_use]
+    pub fn rng(self, seed: u64) -> StdRng {
+        StdRng::seed_from_u64(self.seed(seed))
+    }
+}
+
+/// SplitMix64 output function, a bijective mix of all bits of `x`
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streams_of_consecutive_seeds_are_distinct() {
+        let seeds: Vec<u64> = (0..100)
+            .flat_map(|seed| [Stream::Customers.seed(seed), Stream::Decisions.seed(seed)])
+            .collect();
+        let mut unique = seeds.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), seeds.len());
+
+        // The decisions of one replication are not the customers of the next
+        for seed in 0..100 {
+            assert_ne!(
+                Stream::Decisions.seed(seed),
+                Stream::Customers.seed(seed + 1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_stream_seed_is_deterministic() {
+        assert_eq!(Stream::Customers.seed(42), Stream::Customers.seed(42));
+        assert_ne!(Stream::Customers.seed(42), Stream::Decisions.seed(42));
+    }
+}
@@ -1,14 +1,33 @@
+use crate::analytics::QueueingModel;
+use crate::arrival::ArrivalProfile;
+use crate::balking::BalkingRule;
+use crate::breakdown::{Breakdowns, Interruption};
+use crate::class::{CustomerClass, choose_class, validate_classes};
 use crate::clock::SimClock;
-use crate::customer::Customer;
-use crate::duration::{format_duration, format_duration_fixed_width};
+use crate::closing::ClosingMode;
+use crate::cost::CostModel;
+use crate::customer::{Customer, Outcome};
+use crate::distribution::ServiceDistribution;
+use crate::duration::format_duration_fixed_width;
 use crate::event::EventType;
-use crate::output::OutputMessage;
+use crate::histogram::HistogramBins;
+use crate::lane::LaneOverflow;
+use crate::output::{OutputMessage, print_log_footer, print_log_header, spawn_event_printer};
+use crate::queue::{QueueDiscipline, WaitingQueue};
+use crate::routing::WindowLanes;
+use crate::seed::Stream;
+use crate::staffing::{Shift, StaffingSchedule};
 use crate::state::SimState;
-use crate::statistics::Statistics;
-use rand::Rng;
-use std::io::{self, Write};
+use crate::statistics::{ClassStatistics, SegmentStatistics, Statistics};
+use crate::window::{Window, validate_windows};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::io;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc;
+use tokio::sync::{Notify, mpsc};
 
 /// A discrete-event simulation of a drive-through service system.
 ///
@@ -17,6 +36,13 @@ use tokio::sync::mpsc;
 pub struct Simulation {
     clock: Arc<SimClock>,
     pub state: Arc<Mutex<SimState>>,
+    /// Whether the event log is suppressed
+    quiet: bool,
+    /// CSV file receiving one row per customer (not written when None)
+    customer_file: Option<String>,
+    /// Generator of the random customers, seeded with the simulation seed (the thread-local
+    /// generator is used when None)
+    customer_rng: Option<StdRng>,
 }
 
 impl Simulation {
@@ -32,38 +58,394 @@ impl Simulation {
             clock: Arc::new(SimClock::new()),
             state: Arc::new(Mutex::new(SimState {
                 customers: Vec::new(),
+                waiting_queues: vec![WaitingQueue::default()],
+                queue_ready: vec![Arc::new(Notify::new())],
                 waiting_queue_len: 0,
                 busy_servers: 0,
                 num_windows,
                 csv_file: None,
+                customer_file: None,
                 output_tx: None,
                 current_time: 0.0,
                 stats: Statistics::new(),
+                seed: None,
+                rng: StdRng::from_rng(&mut rand::rng()),
+                balking: None,
+                patience: None,
+                renege_timers: HashMap::new(),
+                lane_capacity: None,
+                lane_overflow: LaneOverflow::default(),
+                lane_space: Arc::new(Notify::new()),
+                classes: Vec::new(),
+                class_waiting: Vec::new(),
+                class_busy: Vec::new(),
+                window_lanes: None,
+                windows: Vec::new(),
+                window_customer: Vec::new(),
+                route_turn: 0,
+                staffing: None,
+                window_open: vec![true; num_windows],
+                breakdowns: None,
+                window_down: vec![false; num_windows],
+                closing: ClosingMode::default(),
+                warmup: None,
             })),
+            quiet: false,
+            customer_file: None,
+            customer_rng: None,
         }
     }
 
+    /// Creates a new simulation whose random customer generation is reproducible
+    ///
+    /// Two simulations created with the same seed and configured identically
+    /// generate exactly the same customers.
+    ///
+    /// # Panics
+    /// Panics if `num_windows` is 0
+    #[must_use]
+    pub fn with_seed(num_windows: usize, seed: u64) -> Self {
+        let mut sim = Self::new(num_windows);
+        sim.customer_rng = Some(Stream::Customers.rng(seed));
+        {
+            let mut state = sim.state.lock().unwrap();
+            state.seed = Some(seed);
+            state.rng = Stream::Decisions.rng(seed);
+        }
+        sim
+    }
+
+    /// Sets the rule by which arriving customers balk (drive past) when the queue is long
+    ///
+    /// # Panics
+    /// Panics if the rule is invalid
+    pub fn set_balking(&mut self, rule: BalkingRule) {
+        if let Err(e) = rule.validate() {
+            panic!("Invalid balking rule: {}", e);
+        }
+        self.state.lock().unwrap().balking = Some(rule);
+    }
+
+    /// Limits the number of cars that fit in the lane while waiting for a window
+    ///
+    /// Arriving cars that find `capacity` cars waiting are handled according to `overflow`.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is 0
+    pub fn set_lane_capacity(&mut self, capacity: usize, overflow: LaneOverflow) {
+        assert!(capacity > 0, "Lane capacity must be greater than 0");
+        let mut state = self.state.lock().unwrap();
+        state.lane_capacity = Some(capacity);
+        state.lane_overflow = overflow;
+        // With window lanes, the entrance is only full once every lane is
+        let lanes = state.waiting_queues.len();
+        state.stats.set_lane_capacity(capacity * lanes);
+    }
+
+    /// Sets the distribution from which waiting customers draw their patience
+    ///
+    /// A customer still waiting in the queue once their patience runs out leaves (reneges).
+    /// Customers added with an explicit patience keep it.
+    ///
+    /// # Panics
+    /// Panics if the distribution is invalid
+    pub fn set_patience(&mut self, distribution: ServiceDistribution) {
+        if let Err(e) = distribution.validate() {
+            panic!("Invalid patience distribution: {}", e);
+        }
+        self.state.lock().unwrap().patience = Some(distribution);
+    }
+
+    /// Sets the customer classes
+    ///
+    /// Each class is tracked separately in the statistics. Waiting customers of a higher
+    /// priority class are served first with [`QueueDiscipline::Priority`] (see
+    /// [`Simulation::set_queue_discipline`]).
+    ///
+    /// # Panics
+    /// Panics if the classes are invalid or customers were already added
+    pub fn set_classes(&mut self, classes: Vec<CustomerClass>) {
+        if let Err(e) = validate_classes(&classes) {
+            panic!("Invalid customer classes: {}", e);
+        }
+        let mut state = self.state.lock().unwrap();
+        assert!(
+            state.customers.is_empty(),
+            "Classes must be set before adding customers"
+        );
+        state.stats.set_classes(
+            classes
+                .iter()
+                .map(|c| ClassStatistics {
+                    name: c.name.clone(),
+                    priority: c.priority,
+                    stats: Statistics::new(),
+                })
+                .collect(),
+        );
+        state.class_waiting = vec![0; classes.len()];
+        state.class_busy = vec![0; classes.len()];
+        state.classes = classes;
+    }
+
+    /// Sets the order in which waiting customers are served
+    ///
+    /// # Panics
+    /// Panics if customers are already waiting
+    pub fn set_queue_discipline(&mut self, discipline: QueueDiscipline) {
+        let mut state = self.state.lock().unwrap();
+        assert!(
+            state.waiting_queues.iter().all(WaitingQueue::is_empty),
+            "Customers are already waiting"
+        );
+        for queue in &mut state.waiting_queues {
+            *queue = WaitingQueue::new(discipline);
+        }
+    }
+
+    /// Gives every window its own lane instead of one shared queue
+    ///
+    /// Arriving cars choose a lane by the routing policy and may jockey to an adjacent,
+    /// shorter lane while waiting. Each window is tracked separately in the statistics.
+    ///
+    /// # Panics
+    /// Panics if the lane parameters are invalid or customers are already waiting
+    pub fn set_window_lanes(&mut self, lanes: WindowLanes) {
+        if let Err(e) = lanes.validate() {
+            panic!("Invalid window lanes: {}", e);
+        }
+        let mut state = self.state.lock().unwrap();
+        assert!(
+            state.waiting_queues.iter().all(WaitingQueue::is_empty),
+            "Customers are already waiting"
+        );
+        let num_windows = state.num_windows;
+        let discipline = state.waiting_queues[0].discipline();
+        state.waiting_queues = (0..num_windows)
+            .map(|_| WaitingQueue::new(discipline))
+            .collect();
+        state.queue_ready = (0..num_windows).map(|_| Arc::new(Notify::new())).collect();
+        if let Some(capacity) = state.lane_capacity {
+            state.stats.set_lane_capacity(capacity * num_windows);
+        }
+        state.window_lanes = Some(lanes);
+        track_windows(&mut state);
+    }
+
+    /// Sets the speed and the customer classes served of each window
+    ///
+    /// Service times are divided by the speed of the window serving the customer. Each window
+    /// is tracked separately in the statistics. Classes must be set first
+    /// (see [`Simulation::set_classes`]).
+    ///
+    /// # Panics
+    /// Panics if the windows are invalid, do not match the number of windows, refer to unknown
+    /// classes or leave a class without a window
+    pub fn set_windows(&mut self, windows: Vec<Window>) {
+        let mut state = self.state.lock().unwrap();
+        if let Err(e) = validate_windows(&windows, &state.classes) {
+            panic!("Invalid windows: {}", e);
+        }
+        assert_eq!(
+            windows.len(),
+            state.num_windows,
+            "Expected one definition per window"
+        );
+        state.windows = windows;
+        track_windows(&mut state);
+    }
+
+    /// Opens and closes windows over the day according to a staffing schedule
+    ///
+    /// Outside of the shifts every window is open; during a shift, its first `windows`
+    /// windows are. A window due to close finishes serving its current customer first.
+    /// Utilization is measured against the windows open over time, and each window is
+    /// tracked separately in the statistics.
+    ///
+    /// # Panics
+    /// Panics if shifts overlap, have an invalid time range or staff more windows than exist
+    pub fn set_staffing(&mut self, shifts: Vec<Shift>) {
+        let mut state = self.state.lock().unwrap();
+        let schedule = match StaffingSchedule::new(state.num_windows, shifts) {
+            Ok(schedule) => schedule,
+            Err(e) => panic!("Invalid staffing schedule: {}", e),
+        };
+        state.window_open = (0..state.num_windows)
+            .map(|k| schedule.is_open(k, 0.0))
+            .collect();
+        state.staffing = Some(schedule);
+        track_windows(&mut state);
+    }
+
+    /// Lets every window fail and be repaired according to `breakdowns`
+    ///
+    /// Each window fails independently; windows defined with their own failure process (see
+    /// [`Simulation::set_windows`]) keep it. A window only fails while open. The customer at a
+    /// failing window stays there until it is repaired and then continues or restarts their
+    /// service, depending on the interruption policy. Each window is tracked separately in the
+    /// statistics.
+    ///
+    /// # Panics
+    /// Panics if the distributions are invalid
+    pub fn set_breakdowns(&mut self, breakdowns: Breakdowns) {
+        if let Err(e) = breakdowns.validate() {
+            panic!("Invalid breakdowns: {}", e);
+        }
+        let mut state = self.state.lock().unwrap();
+        state.breakdowns = Some(breakdowns);
+        track_windows(&mut state);
+    }
+
+    /// Sets what happens at closing time, i.e. the `max_time` passed to [`Simulation::run`]
+    ///
+    /// With [`ClosingMode::Drain`], no customer arrives after closing time but everyone
+    /// already in line is served, and the report tells the open hours from the
+    /// overtime needed to clear the lane.
+    pub fn set_closing(&mut self, mode: ClosingMode) {
+        self.state.lock().unwrap().closing = mode;
+    }
+
+    /// Deletes the first `warmup` seconds from the statistics
+    ///
+    /// The run starts with an empty lane; at the end of the warm-up period the statistics
+    /// start over while the simulation continues, so averages reflect the steady state.
+    /// Only applies when [`Simulation::run`] is given a `max_time` after `warmup`.
+    ///
+    /// # Panics
+    /// Panics if `warmup` is not positive
+    pub fn set_warmup(&mut self, warmup: f64) {
+        assert!(warmup > 0.0, "Warm-up period must be positive");
+        self.state.lock().unwrap().warmup = Some(warmup);
+    }
+
+    /// Detects the warm-up period with MSER-5 on the average queue length over intervals
+    /// of width `interval`, and reports it with the statistics
+    ///
+    /// # Panics
+    /// Panics if `interval` is not positive
+    pub fn set_warmup_detection(&mut self, interval: f64) {
+        assert!(interval > 0.0, "Interval must be positive");
+        self.state
+            .lock()
+            .unwrap()
+            .stats
+            .track_queue_length(interval);
+    }
+
+    /// Compares the statistics with the steady state of `model` in the report
+    pub fn set_queueing_model(&mut self, model: QueueingModel) {
+        self.state.lock().unwrap().stats.queueing_model = Some(model);
+    }
+
+    /// Prices the results with `costs` in the report and the metrics
+    pub fn set_costs(&mut self, costs: CostModel) {
+        self.state.lock().unwrap().stats.cost_model = Some(costs);
+    }
+
+    /// Reports histograms of the waiting time and the time in system with the given bins
+    pub fn set_histogram(&mut self, bins: HistogramBins) {
+        self.state.lock().unwrap().stats.set_histogram(bins);
+    }
+
+    /// Tracks arrivals, completions, queue length, utilization and waits per time bucket of
+    /// width `interval`, reported with the statistics (see [`Simulation::write_time_series`])
+    pub fn set_time_series(&mut self, interval: f64) {
+        assert!(interval > 0.0, "Interval must be positive");
+        self.state.lock().unwrap().stats.set_time_series(interval);
+    }
+
+    /// Writes one row per time bucket to `filename` after the run
+    pub fn write_time_series(&self, filename: &str) -> io::Result<()> {
+        let state = self.state.lock().unwrap();
+        state
+            .stats
+            .write_time_series(filename, state.current_time, state.num_windows, state.seed)
+    }
+
+    /// Streams one row per customer to `filename` while running: the times of the visit,
+    /// the wait, service and time in system, and how the visit ended
+    pub fn set_customer_file(&mut self, filename: &str) {
+        self.customer_file = Some(filename.to_string());
+    }
+
+    /// Suppresses the event log printed while running (e.g. for replications)
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+
+    /// Returns the seed used for random customer generation, if one was set
+    #[allow(dead_code)] // Library API, used by the integration tests
+    pub fn seed(&self) -> Option<u64> {
+        self.state.lock().unwrap().seed
+    }
+
     /// Adds a customer to the simulation
     ///
     /// # Panics
     /// Panics if `arrival_time` is negative or `service_duration` is not positive
     pub fn add_customer(&mut self, arrival_time: f64, service_duration: f64) {
+        self.add_customer_with_patience(arrival_time, service_duration, None);
+    }
+
+    /// Adds a customer who leaves the queue (reneges) if not served within `patience`
+    ///
+    /// With `patience` set to None, the customer draws a patience from the
+    /// distribution set by [`Simulation::set_patience`] on arrival, or waits indefinitely.
+    ///
+    /// # Panics
+    /// Panics if `arrival_time` is negative, `service_duration` is not positive or `patience` is negative
+    pub fn add_customer_with_patience(
+        &mut self,
+        arrival_time: f64,
+        service_duration: f64,
+        patience: Option<f64>,
+    ) {
+        self.add_class_customer(arrival_time, service_duration, 0, patience);
+    }
+
+    /// Adds a customer of the class with index `class` (see [`Simulation::set_classes`])
+    ///
+    /// # Panics
+    /// Panics if `arrival_time` is negative, `service_duration` is not positive, `patience`
+    /// is negative or `class` does not refer to a configured class
+    pub fn add_class_customer(
+        &mut self,
+        arrival_time: f64,
+        service_duration: f64,
+        class: usize,
+        patience: Option<f64>,
+    ) {
         assert!(arrival_time >= 0.0, "Arrival time must be non-negative");
         assert!(service_duration > 0.0, "Service duration must be positive");
+        assert!(
+            patience.is_none_or(|p| p >= 0.0),
+            "Patience must be non-negative"
+        );
 
         let mut state = self.state.lock().unwrap();
+        assert!(
+            class < state.classes.len().max(1),
+            "Class index out of range"
+        );
         state.customers.push(Customer {
             arrival_time,
             service_duration,
+            class,
+            patience,
+            window: None,
+            queue_entry_time: None,
             service_start_time: None,
             service_end_time: None,
+            outcome: None,
         });
     }
 
     /// Runs the simulation
     ///
     /// # Arguments
-    /// * `max_time` - Optional maximum simulation time. If None, runs until all customers are served.
+    /// * `max_time` - Optional closing time, after which no customer arrives; by default the
+    ///   simulation stops there (see [`Simulation::set_closing`]). If None, runs until all
+    ///   customers are served.
     /// * `csv_filename` - Optional CSV filename for streaming event history
     pub async fn run(&mut self, max_time: Option<f64>, csv_filename: Option<&str>) {
         // Sort customers by arrival time (critical for correct sequential processing)
@@ -79,60 +461,127 @@ impl Simulation {
         {
             eprintln!("Warning: Failed to create CSV file {}: {}", filename, e);
         }
+        if let Some(filename) = &self.customer_file
+            && let Err(e) = self.state.lock().unwrap().init_customer_csv(filename)
+        {
+            eprintln!("Warning: Failed to create CSV file {}: {}", filename, e);
+        }
 
         // Create output channel for ordered event printing
-        let (output_tx, mut output_rx) = mpsc::unbounded_channel::<OutputMessage>();
+        let (output_tx, output_rx) = mpsc::unbounded_channel::<OutputMessage>();
+
+        // Set the output channel in state (dropped right away when quiet, so nothing is printed)
+        self.state.lock().unwrap().output_tx = (!self.quiet).then_some(output_tx);
 
-        // Set the output channel in state
-        self.state.lock().unwrap().output_tx = Some(output_tx);
+        let class_names: Vec<String> = self
+            .state
+            .lock()
+            .unwrap()
+            .classes
+            .iter()
+            .map(|c| c.name.clone())
+            .collect();
+        let show_classes = !class_names.is_empty();
+        let show_windows = !self.state.lock().unwrap().stats.windows.is_empty();
 
         // Spawn dedicated output thread for ordered printing
-        let output_handle = tokio::spawn(async move {
-            while let Some(msg) = output_rx.recv().await {
-                println!(
-                    "{} {:<15} {:<10} {:<10} {}/{}",
-                    format_duration_fixed_width(msg.time),
-                    format!("{:?}", msg.event),
-                    msg.cust_id,
-                    msg.queue_len,
-                    msg.busy_servers,
-                    msg.num_windows
-                );
-                let _ = io::stdout().flush();
-            }
+        let output_handle = spawn_event_printer(output_rx, move |msg| {
+            let class = msg.class.map_or("", |c| class_names[c].as_str());
+            let window = match msg.window {
+                Some(k) if show_windows => format!("{:<7} ", k + 1),
+                _ if show_windows => format!("{:<7} ", ""),
+                _ => String::new(),
+            };
+            format!(
+                "{} {:<15} {:<10} {:<10} {:<11} {}{}",
+                format_duration_fixed_width(msg.time),
+                format!("{:?}", msg.event),
+                msg.cust_id.map_or(String::new(), |id| id.to_string()),
+                msg.queue_len,
+                format!("{}/{}", msg.busy_servers, msg.num_windows),
+                window,
+                class
+            )
         });
 
-        println!("Starting simulation (Coroutine-based)...");
-        println!(
-            "{:>30} {:<15} {:<10} {:<10} BusyServers",
-            "Time", "Event", "CustID", "Queue"
-        );
-        println!(
-            "-------------------------------------------------------------------------------------------"
-        );
-        let _ = io::stdout().flush();
+        if !self.quiet {
+            print_log_header(
+                "Starting simulation (Coroutine-based)...",
+                &format!(
+                    "{:>30} {:<15} {:<10} {:<10} {:<11} {}{}",
+                    "Time",
+                    "Event",
+                    "CustID",
+                    "Queue",
+                    "BusyServers",
+                    if show_windows { "Window  " } else { "" },
+                    if show_classes { "Class" } else { "" }
+                ),
+            );
+        }
 
-        let (tx, rx) = mpsc::channel::<usize>(1000);
-        let shared_rx = Arc::new(tokio::sync::Mutex::new(rx));
-        let num_windows = self.state.lock().unwrap().num_windows;
+        // Waiting customers live in `SimState::waiting_queues` so that they can leave them
+        // (renege, jockey) before a server takes them; servers are woken through `queue_ready`
+        let arrivals_done = Rc::new(Cell::new(false));
+        let (num_windows, queue_ready, closing, warmup) = {
+            let s = self.state.lock().unwrap();
+            (s.num_windows, s.queue_ready.clone(), s.closing, s.warmup)
+        };
 
         let local = tokio::task::LocalSet::new();
 
-        for _ in 0..num_windows {
+        for window in 0..num_windows {
             let state = self.state.clone();
             let clock = self.clock.clone();
-            let rx = shared_rx.clone();
+            let queue_notify = queue_ready[window.min(queue_ready.len() - 1)].clone();
+            let arrivals_done = arrivals_done.clone();
             local.spawn_local(async move {
+                let mut next_failure = state.lock().unwrap().next_failure(window, clock.now());
                 loop {
-                    let cust_id = {
-                        let mut rx_lock = rx.lock().await;
-                        match rx_lock.recv().await {
-                            Some(id) => id,
-                            None => break,
+                    if next_failure.is_some_and(|at| at <= clock.now()) {
+                        next_failure = break_down(&state, &clock, window).await;
+                        continue;
+                    }
+
+                    // Windows follow the staffing schedule between customers, so a window
+                    // due to close serves its current customer first
+                    let (next, open, waiting, shift_change) = {
+                        let mut s = state.lock().unwrap();
+                        let now = clock.now();
+                        let open = s.staffing.as_ref().is_none_or(|st| st.is_open(window, now));
+                        if open != s.window_open[window] {
+                            s.set_window_open(now, window, open);
+                        }
+                        let next = if open { s.next_customer(window) } else { None };
+                        let shift_change = s
+                            .staffing
+                            .as_ref()
+                            .and_then(|st| st.next_change(window, now));
+                        (next, open, s.waiting_queue_len, shift_change)
+                    };
+                    let cust_id = match next {
+                        Some(id) => id,
+                        // A closed window keeps waiting for its next shift while customers
+                        // are left that it may have to serve
+                        None if arrivals_done.get() && (open || waiting == 0) => break,
+                        None => {
+                            // Idle windows also wake up to fail or follow their shifts
+                            let wake_at = [shift_change, next_failure]
+                                .into_iter()
+                                .flatten()
+                                .reduce(f64::min);
+                            match wake_at {
+                                Some(at) => tokio::select! {
+                                    () = queue_notify.notified() => {}
+                                    () = clock.sleep_until(at) => {}
+                                },
+                                None => queue_notify.notified().await,
+                            }
+                            continue;
                         }
                     };
 
-                    let (duration, _now) = {
+                    let (duration, restart) = {
                         let mut s = state.lock().unwrap();
 
                         // Validate customer ID
@@ -156,12 +605,46 @@ impl Simulation {
                             eprintln!("Warning: Queue underflow prevented at T={}", clock.now());
                         }
 
+                        s.leave_queue(cust_id, true);
+
+                        // The customer made it to a window, so they will no longer renege
+                        if let Some(timer) = s.renege_timers.remove(&cust_id) {
+                            timer.abort();
+                        }
+                        s.lane_space.notify_one();
+
+                        if let Some(at_window) = s.window_customer.get_mut(window) {
+                            *at_window = Some(cust_id);
+                            s.customers[cust_id].window = Some(window);
+                        }
                         s.customers[cust_id].service_start_time = Some(now);
                         s.record_history(now, EventType::ServiceStart, cust_id);
-                        (s.customers[cust_id].service_duration, now)
+                        s.notify_if_drained(arrivals_done.get());
+                        let restart = s
+                            .breakdowns_of(window)
+                            .is_some_and(|b| b.interruption == Interruption::Restart);
+                        (s.service_time(window, cust_id), restart)
                     };
 
-                    clock.sleep(duration).await;
+                    // A failure interrupts the service until the window is repaired
+                    let mut remaining = duration;
+                    loop {
+                        let now = clock.now();
+                        match next_failure {
+                            Some(at) if at < now + remaining => {
+                                clock.sleep_until(at).await;
+                                remaining -= at - now;
+                                next_failure = break_down(&state, &clock, window).await;
+                                if restart {
+                                    remaining = duration;
+                                }
+                            }
+                            _ => {
+                                clock.sleep(remaining).await;
+                                break;
+                            }
+                        }
+                    }
 
                     {
                         let mut s = state.lock().unwrap();
@@ -172,9 +655,14 @@ impl Simulation {
 
                         // Now change state
                         s.busy_servers -= 1;
+                        s.finish_service(cust_id);
+                        if let Some(at_window) = s.window_customer.get_mut(window) {
+                            *at_window = None;
+                        }
 
                         s.customers[cust_id].service_end_time = Some(now);
                         s.record_history(now, EventType::ServiceEnd, cust_id);
+                        s.rebalance(now);
                     }
                 }
             });
@@ -184,92 +672,174 @@ impl Simulation {
         let arrival_clock = self.clock.clone();
         local.spawn_local(async move {
             let customers_len = arrival_state.lock().unwrap().customers.len();
-            for i in 0..customers_len {
+            'arrivals: for i in 0..customers_len {
                 let arrival_time = arrival_state.lock().unwrap().customers[i].arrival_time;
                 if max_time.is_some_and(|limit| arrival_time > limit) {
                     break;
                 }
                 arrival_clock.sleep_until(arrival_time).await;
 
-                // First, send customer to queue to guarantee FIFO order
-                // This ensures the channel receives customers in arrival order
-                if tx.send(i).await.is_err() {
-                    eprintln!(
-                        "Warning: All servers shut down prematurely at T={}",
-                        arrival_time
-                    );
-                    break;
-                }
-
-                // Then update state and record arrival
+                // Customers who see a long queue may drive past without joining
                 {
-                    let mut s = arrival_state.lock().unwrap();
-                    s.update_integral(arrival_time);
-                    s.waiting_queue_len += 1;
-                    s.record_history(arrival_time, EventType::Arrival, i);
+                    let mut guard = arrival_state.lock().unwrap();
+                    let s = &mut *guard;
+                    let queue_len = s.visible_queue_len(i);
+                    if let Some(rule) = &s.balking
+                        && rule.should_balk(&mut s.rng, queue_len)
+                    {
+                        s.update_integral(arrival_time);
+                        s.record_history(arrival_time, EventType::Balk, i);
+                        continue;
+                    }
                 }
-            }
-            drop(tx);
-        });
-
-        local
-            .run_until(async {
-                let mut no_advance_count = 0;
-                const MAX_NO_ADVANCE: usize = 100;
 
+                // A full lane either turns the car away or blocks it on the street until
+                // a space frees up; cars behind a blocked car cannot enter either
+                let mut blocked = false;
                 loop {
-                    tokio::task::yield_now().await;
+                    let lane_space = {
+                        let mut s = arrival_state.lock().unwrap();
+                        if s.lane_has_room(i) {
+                            break;
+                        }
+                        let now = arrival_clock.now();
+                        match s.lane_overflow {
+                            LaneOverflow::Reject => {
+                                s.update_integral(now);
+                                s.record_history(now, EventType::Reject, i);
+                                continue 'arrivals;
+                            }
+                            LaneOverflow::Block => {
+                                if !blocked {
+                                    s.update_integral(now);
+                                    s.record_history(now, EventType::Blocked, i);
+                                    blocked = true;
+                                }
+                                s.lane_space.clone()
+                            }
+                        }
+                    };
+                    lane_space.notified().await;
+                }
 
-                    if max_time.is_some_and(|limit| self.clock.now() >= limit) {
-                        break;
-                    }
+                let patience = {
+                    let mut guard = arrival_state.lock().unwrap();
+                    let s = &mut *guard;
+                    let now = arrival_clock.now();
 
-                    if !self.clock.advance() {
-                        // No events to advance - check if we're deadlocked or truly done
-                        no_advance_count += 1;
+                    // Cars stuck on the street enter later than they arrived
+                    if now > arrival_time {
+                        s.stats.record_blocked(now - arrival_time);
+                        if let Some(class) = s.stats.classes.get_mut(s.customers[i].class) {
+                            class.stats.record_blocked(now - arrival_time);
+                        }
+                    }
 
-                        if no_advance_count > MAX_NO_ADVANCE {
-                            // Potential deadlock - check if there are customers still in system
-                            let state = self.state.lock().unwrap();
-                            let customers_in_system = state.waiting_queue_len + state.busy_servers;
+                    // Join the queue (or pick a lane) and wake the window serving it
+                    s.update_integral(now);
+                    s.customers[i].queue_entry_time = Some(now);
+                    s.enqueue(i);
+                    s.record_history(now, EventType::Arrival, i);
+                    s.rebalance(now);
 
-                            if customers_in_system > 0 {
-                                eprintln!(
-                                    "Warning: Deadlock detected with {} customers still in system",
-                                    customers_in_system
-                                );
-                            }
-                            break;
-                        }
-                    } else {
-                        // Successfully advanced, reset counter
-                        no_advance_count = 0;
+                    // Customers without an explicit patience draw one from the distribution
+                    if s.customers[i].patience.is_none()
+                        && let Some(dist) = &s.patience
+                    {
+                        s.customers[i].patience = Some(dist.sample(&mut s.rng));
                     }
+                    s.customers[i].patience.map(|patience| (now, patience))
+                };
+
+                // Patience runs from joining the lane: time blocked on the street is not
+                // spent in the queue
+                if let Some((entry_time, patience)) = patience {
+                    let state = arrival_state.clone();
+                    let clock = arrival_clock.clone();
+                    let arrivals_done = arrivals_done.clone();
+                    let timer = tokio::task::spawn_local(async move {
+                        clock.sleep_until(entry_time + patience).await;
+                        let mut s = state.lock().unwrap();
+                        s.renege(clock.now(), i);
+                        s.notify_if_drained(arrivals_done.get());
+                    });
+                    arrival_state
+                        .lock()
+                        .unwrap()
+                        .renege_timers
+                        .insert(i, timer.abort_handle());
                 }
-            })
+            }
+
+            // When draining, the store stays open until closing time before serving only the
+            // customers still in line
+            if closing == ClosingMode::Drain
+                && let Some(limit) = max_time
+            {
+                arrival_clock.sleep_until(limit).await;
+                let mut s = arrival_state.lock().unwrap();
+                let now = arrival_clock.now();
+                s.update_integral(now);
+                let in_system = s.waiting_queue_len + s.busy_servers;
+                s.stats.record_closing(now, in_system);
+            }
+
+            // Let idle servers shut down once the queue is drained
+            arrivals_done.set(true);
+            for queue_notify in &queue_ready {
+                queue_notify.notify_waiters();
+            }
+        });
+
+        // The statistics start over at the end of the warm-up period; without a closing
+        // time, the warm-up could outlast the customers and extend the run
+        if let Some(warmup) = warmup
+            && max_time.is_some_and(|limit| warmup < limit)
+        {
+            let state = self.state.clone();
+            let clock = self.clock.clone();
+            local.spawn_local(async move {
+                clock.sleep_until(warmup).await;
+                let mut s = state.lock().unwrap();
+                let now = clock.now();
+                s.update_integral(now);
+                let in_system = s.waiting_queue_len + s.busy_servers;
+                let queue_len = s.waiting_queue_len;
+                s.stats.reset(now, in_system);
+                s.stats.update_max_queue(queue_len);
+            });
+        }
+
+        let clock_limit = match closing {
+            ClosingMode::Hard => max_time,
+            ClosingMode::Drain => None,
+        };
+        local
+            .run_until(self.clock.run(clock_limit, || {
+                let state = self.state.lock().unwrap();
+                state.waiting_queue_len + state.busy_servers
+            }))
             .await;
 
         // Finalize state tracking
         {
             let mut s = self.state.lock().unwrap();
-            let final_time = if let Some(limit) = max_time {
-                limit
-            } else {
+            let final_time = match (max_time, closing) {
+                (Some(limit), ClosingMode::Hard) => limit,
+                // The lane may be cleared before closing time
+                (Some(limit), ClosingMode::Drain) => self.clock.now().max(limit),
                 // Natural completion - use clock time
-                self.clock.now()
+                (None, _) => self.clock.now(),
             };
 
             // Update integrals for final time period if needed
             if s.current_time < final_time {
-                let queue_len = s.waiting_queue_len;
-                let busy_servers = s.busy_servers;
-                s.stats
-                    .update_integrals(final_time, queue_len, busy_servers);
-                s.current_time = final_time;
+                s.update_integral(final_time);
             }
 
-            // Close CSV file first
+            // Close CSV files first
             s.close_csv();
+            s.close_customer_csv(final_time);
 
             // Close output channel to signal output thread to finish
             s.output_tx = None;
@@ -278,24 +848,77 @@ impl Simulation {
         // Wait for output thread to finish printing all messages
         let _ = output_handle.await;
 
-        println!(
-            "-------------------------------------------------------------------------------------------"
-        );
-        println!(
-            "Simulation finished at T={}",
-            format_duration(self.state.lock().unwrap().current_time)
-        );
+        if !self.quiet {
+            print_log_footer(self.state.lock().unwrap().current_time);
+        }
     }
 
     /// Prints detailed statistics about the simulation results
     pub fn print_statistics(&self) {
         let state = self.state.lock().unwrap();
-        state
-            .stats
-            .print_report(state.current_time, state.customers.len(), state.num_windows);
+        state.stats.print_report(
+            state.current_time,
+            state.customers.len(),
+            state.num_windows,
+            state.seed,
+        );
     }
 
-    /// Generates random customers using exponential inter-arrival times
+    /// Returns the waiting times of the customers arriving in `[from, to)` who reached a
+    /// window, sorted in ascending order
+    #[allow(dead_code)] // Library API, used by the integration tests
+    pub fn waiting_times(&self, from: f64, to: f64) -> Vec<f64> {
+        let state = self.state.lock().unwrap();
+        let mut waits: Vec<f64> = state
+            .customers
+            .iter()
+            .filter(|c| c.arrival_time >= from && c.arrival_time < to)
+            .filter_map(|c| c.service_start_time.map(|start| start - c.arrival_time))
+            .collect();
+        waits.sort_by(f64::total_cmp);
+        waits
+    }
+
+    /// Returns the waiting times of every customer arriving in `[from, to)` before the end
+    /// of the run, sorted in ascending order
+    ///
+    /// Customers who left the queue or were still waiting at the end count with their time
+    /// in the queue so far (censored waits); customers who balked or were turned away
+    /// count as waiting forever.
+    pub fn censored_waiting_times(&self, from: f64, to: f64) -> Vec<f64> {
+        let state = self.state.lock().unwrap();
+        let now = state.current_time;
+        let mut waits: Vec<f64> = state
+            .customers
+            .iter()
+            .filter(|c| c.arrival_time >= from && c.arrival_time < to && c.arrival_time <= now)
+            .map(|c| match (c.service_start_time, c.outcome) {
+                (Some(start), _) => start - c.arrival_time,
+                (None, Some(Outcome::Balked | Outcome::Rejected)) => f64::INFINITY,
+                (None, Some(Outcome::Reneged)) => {
+                    let entry = c.queue_entry_time.unwrap_or(c.arrival_time);
+                    let left = c
+                        .patience
+                        .map_or(now, |patience| (entry + patience).min(now));
+                    left - c.arrival_time
+                }
+                (None, _) => now - c.arrival_time,
+            })
+            .collect();
+        waits.sort_by(f64::total_cmp);
+        waits
+    }
+
+    /// Returns the key performance measures of the run (see [`Statistics::metrics`])
+    pub fn metrics(&self) -> Vec<(&'static str, f64)> {
+        let state = self.state.lock().unwrap();
+        state.stats.metrics(state.current_time, state.num_windows)
+    }
+
+    /// Generates random customers using exponential inter-arrival times and uniform service times
+    ///
+    /// Shorthand for [`Simulation::generate_customers`] with a
+    /// [`ServiceDistribution::Uniform`] service time distribution.
     ///
     /// # Arguments
     /// * `max_time` - Maximum simulation time
@@ -305,6 +928,7 @@ impl Simulation {
     ///
     /// # Panics
     /// Panics if any arguments are invalid (e.g., negative or zero values)
+    #[allow(dead_code)] // Library API, used by the integration tests
     pub fn generate_random_customers(
         &mut self,
         max_time: f64,
@@ -312,31 +936,217 @@ impl Simulation {
         min_service: f64,
         max_service: f64,
     ) {
-        assert!(max_time > 0.0, "Max time must be positive");
-        assert!(
-            avg_arrival_interval > 0.0,
-            "Average arrival interval must be positive"
+        let service = ServiceDistribution::Uniform {
+            min: min_service,
+            max: max_service,
+        };
+        self.generate_customers(
+            max_time,
+            &ArrivalProfile::constant(avg_arrival_interval),
+            &service,
         );
-        assert!(min_service > 0.0, "Minimum service time must be positive");
-        assert!(
-            max_service >= min_service,
-            "Maximum service time must be >= minimum service time"
+    }
+
+    /// Generates random customers using exponential inter-arrival times and uniform service
+    /// times drawn from `rng`
+    ///
+    /// Shorthand for [`Simulation::generate_customers_with_rng`] with a
+    /// [`ServiceDistribution::Uniform`] service time distribution.
+    ///
+    /// # Panics
+    /// Panics if any arguments are invalid (e.g., negative or zero values)
+    #[allow(dead_code)] // Library API, used by the integration tests
+    pub fn generate_random_customers_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        max_time: f64,
+        avg_arrival_interval: f64,
+        min_service: f64,
+        max_service: f64,
+    ) {
+        let service = ServiceDistribution::Uniform {
+            min: min_service,
+            max: max_service,
+        };
+        self.generate_customers_with_rng(
+            rng,
+            max_time,
+            &ArrivalProfile::constant(avg_arrival_interval),
+            &service,
         );
+    }
 
-        let mut rng = rand::rng();
-        let mut current_arrival = 0.0;
+    /// Generates random customers arriving according to a (possibly time-varying) Poisson process
+    ///
+    /// Uses a generator seeded with the simulation seed if one was set (see [`Simulation::with_seed`]),
+    /// otherwise the thread-local generator, so results differ between runs. The seeded
+    /// generator lives as long as the simulation: further calls continue its stream.
+    ///
+    /// # Arguments
+    /// * `max_time` - Maximum simulation time
+    /// * `arrivals` - Arrival rate profile
+    /// * `service` - Distribution of service times
+    ///
+    /// # Panics
+    /// Panics if any arguments are invalid (e.g., negative or zero values)
+    pub fn generate_customers(
+        &mut self,
+        max_time: f64,
+        arrivals: &ArrivalProfile,
+        service: &ServiceDistribution,
+    ) {
+        match self.customer_rng.take() {
+            Some(mut rng) => {
+                self.generate_customers_with_rng(&mut rng, max_time, arrivals, service);
+                self.customer_rng = Some(rng);
+            }
+            None => self.generate_customers_with_rng(&mut rand::rng(), max_time, arrivals, service),
+        }
+    }
 
-        loop {
-            let u: f64 = 1.0 - rng.random::<f64>();
-            let interval = -u.ln() * avg_arrival_interval;
-            current_arrival += interval;
+    /// Generates random customers arriving according to a (possibly time-varying) Poisson process,
+    /// drawing from `rng`
+    ///
+    /// Passing a seeded generator makes the generated customers reproducible.
+    /// Each segment of the arrival profile is tracked separately in the statistics.
+    ///
+    /// # Arguments
+    /// * `rng` - Random number generator to draw arrivals and service times from
+    /// * `max_time` - Maximum simulation time
+    /// * `arrivals` - Arrival rate profile
+    /// * `service` - Distribution of service times
+    ///
+    /// # Panics
+    /// Panics if any arguments are invalid (e.g., negative or zero values)
+    pub fn generate_customers_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        max_time: f64,
+        arrivals: &ArrivalProfile,
+        service: &ServiceDistribution,
+    ) {
+        if let Err(e) = service.validate() {
+            panic!("Invalid service distribution: {}", e);
+        }
+        for arrival_time in self.generate_arrivals(rng, max_time, arrivals) {
+            let service_time = service.sample(rng);
+            self.add_customer(arrival_time, service_time);
+        }
+    }
 
-            if current_arrival > max_time {
-                break;
+    /// Generates random customers of the configured classes (see [`Simulation::set_classes`])
+    ///
+    /// Each customer's class is drawn according to the class shares and their service time
+    /// from the class's service distribution. Uses the simulation seed like
+    /// [`Simulation::generate_customers`].
+    ///
+    /// # Panics
+    /// Panics if no classes are set, a class has no service distribution, or any other
+    /// argument is invalid
+    pub fn generate_class_customers(&mut self, max_time: f64, arrivals: &ArrivalProfile) {
+        match self.customer_rng.take() {
+            Some(mut rng) => {
+                self.generate_class_customers_with_rng(&mut rng, max_time, arrivals);
+                self.customer_rng = Some(rng);
             }
+            None => self.generate_class_customers_with_rng(&mut rand::rng(), max_time, arrivals),
+        }
+    }
+
+    /// Generates random customers of the configured classes, drawing from `rng`
+    ///
+    /// # Panics
+    /// Panics if no classes are set, a class has no service distribution, or any other
+    /// argument is invalid
+    pub fn generate_class_customers_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        max_time: f64,
+        arrivals: &ArrivalProfile,
+    ) {
+        let classes = self.state.lock().unwrap().classes.clone();
+        assert!(!classes.is_empty(), "No customer classes are set");
+        let services: Vec<ServiceDistribution> = classes
+            .iter()
+            .map(|c| match &c.service {
+                Some(service) => service.clone(),
+                None => panic!("Class '{}' has no service distribution", c.name),
+            })
+            .collect();
 
-            let service = rng.random_range(min_service..=max_service);
-            self.add_customer(current_arrival, service);
+        for arrival_time in self.generate_arrivals(rng, max_time, arrivals) {
+            let class = choose_class(rng, &classes);
+            let service_time = services[class].sample(rng);
+            self.add_class_customer(arrival_time, service_time, class, None);
         }
     }
+
+    /// Validates the arrival profile, registers its segments in the statistics and
+    /// draws the arrival times
+    fn generate_arrivals<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        max_time: f64,
+        arrivals: &ArrivalProfile,
+    ) -> Vec<f64> {
+        assert!(max_time > 0.0, "Max time must be positive");
+        if let Err(e) = arrivals.validate() {
+            panic!("Invalid arrival profile: {}", e);
+        }
+
+        self.state.lock().unwrap().stats.set_segments(
+            arrivals
+                .segments()
+                .iter()
+                .map(|s| SegmentStatistics::new(s.label(), s.from, s.to))
+                .collect(),
+        );
+
+        arrivals.generate_arrivals(rng, max_time)
+    }
+}
+
+/// Takes `window` out of service for a repair, returning the time of its next failure
+///
+/// A closed window does not fail; it only draws its next failure time.
+async fn break_down(state: &Arc<Mutex<SimState>>, clock: &SimClock, window: usize) -> Option<f64> {
+    let repair_time = {
+        let mut s = state.lock().unwrap();
+        let now = clock.now();
+        if !s.window_open[window] {
+            return s.next_failure(window, now);
+        }
+        s.set_window_down(now, window, true);
+        s.repair_time(window)
+    };
+    clock.sleep(repair_time).await;
+
+    let mut s = state.lock().unwrap();
+    let now = clock.now();
+    s.set_window_down(now, window, false);
+    s.next_failure(window, now)
+}
+
+/// Registers a statistics tracker for every window, labelled with its definition if it has one
+fn track_windows(state: &mut SimState) {
+    let labels = (0..state.num_windows)
+        .map(|k| match state.windows.get(k) {
+            Some(w) if w.classes.is_empty() => {
+                format!("Window {} ({}, speed {:.2})", k + 1, w.name, w.speed)
+            }
+            Some(w) => format!(
+                "Window {} ({}, speed {:.2}, serves {})",
+                k + 1,
+                w.name,
+                w.speed,
+                w.classes.join(", ")
+            ),
+            None => format!("Window {}", k + 1),
+        })
+        .collect();
+    state.window_customer = vec![None; state.num_windows];
+    state
+        .stats
+        .set_windows(labels, state.window_lanes.is_some());
+    state.track_open_windows();
 }
@@ -0,0 +1,165 @@
+use crate::duration::{deserialize_duration, format_duration};
+use serde::Deserialize;
+
+/// A period of the day with a given number of staffed windows
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Shift {
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub from: f64,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub to: f64,
+    /// Number of windows open during the shift
+    pub windows: usize,
+}
+
+impl Shift {
+    /// Returns the time range of the shift for messages
+    pub fn label(&self) -> String {
+        format!(
+            "{} - {}",
+            format_duration(self.from),
+            format_duration(self.to)
+        )
+    }
+
+    /// Returns true if `time` falls within `[from, to)`
+    pub fn contains(&self, time: f64) -> bool {
+        time >= self.from && time < self.to
+    }
+}
+
+/// Number of staffed windows over the day
+///
+/// Outside of the configured shifts, every window is open. With `n` windows staffed,
+/// windows 1 to `n` are open, so the first windows are the last to close.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaffingSchedule {
+    num_windows: usize,
+    shifts: Vec<Shift>,
+}
+
+impl StaffingSchedule {
+    /// Creates a schedule for `num_windows` windows from the given shifts
+    ///
+    /// Shifts are sorted by start time.
+    pub fn new(num_windows: usize, mut shifts: Vec<Shift>) -> Result<Self, String> {
+        shifts.sort_by(|a, b| a.from.total_cmp(&b.from));
+        let schedule = Self {
+            num_windows,
+            shifts,
+        };
+        schedule.validate()?;
+        Ok(schedule)
+    }
+
+    /// Checks that shifts have a valid time range, do not overlap and do not staff more
+    /// windows than there are
+    pub fn validate(&self) -> Result<(), String> {
+        for shift in &self.shifts {
+            if shift.from < 0.0 || shift.to <= shift.from {
+                return Err(format!(
+                    "shift '{}': expected 0 <= from < to",
+                    shift.label()
+                ));
+            }
+            if shift.windows > self.num_windows {
+                return Err(format!(
+                    "shift '{}': {} windows staffed but only {} exist",
+                    shift.label(),
+                    shift.windows,
+                    self.num_windows
+                ));
+            }
+        }
+        for pair in self.shifts.windows(2) {
+            if pair[1].from < pair[0].to {
+                return Err(format!(
+                    "shifts '{}' and '{}' overlap",
+                    pair[0].label(),
+                    pair[1].label()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the shifts, sorted by start time
+    #[allow(dead_code)] // Library API, used by the integration tests
+    pub fn shifts(&self) -> &[Shift] {
+        &self.shifts
+    }
+
+    /// Returns the number of windows staffed at `time`
+    pub fn staffed_at(&self, time: f64) -> usize {
+        self.shifts
+            .iter()
+            .find(|s| s.contains(time))
+            .map_or(self.num_windows, |s| s.windows)
+    }
+
+    /// Returns true if `window` is staffed at `time`
+    pub fn is_open(&self, window: usize, time: f64) -> bool {
+        window < self.staffed_at(time)
+    }
+
+    /// Returns the first time after `time` at which `window` opens or closes
+    pub fn next_change(&self, window: usize, time: f64) -> Option<f64> {
+        let open = self.is_open(window, time);
+        self.shifts
+            .iter()
+            .flat_map(|s| [s.from, s.to])
+            .filter(|&t| t > time)
+            .find(|&t| self.is_open(window, t) != open)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shift(from: f64, to: f64, windows: usize) -> Shift {
+        Shift { from, to, windows }
+    }
+
+    #[test]
+    fn test_staffed_at() {
+        let schedule =
+            StaffingSchedule::new(3, vec![shift(600.0, 900.0, 1), shift(0.0, 300.0, 2)]).unwrap();
+
+        assert_eq!(schedule.staffed_at(0.0), 2);
+        assert_eq!(schedule.staffed_at(300.0), 3);
+        assert_eq!(schedule.staffed_at(899.0), 1);
+        assert_eq!(schedule.staffed_at(900.0), 3);
+        assert!(schedule.is_open(1, 100.0));
+        assert!(!schedule.is_open(2, 100.0));
+    }
+
+    #[test]
+    fn test_next_change() {
+        let schedule = StaffingSchedule::new(
+            3,
+            vec![
+                shift(0.0, 300.0, 2),
+                shift(300.0, 600.0, 1),
+                shift(600.0, 900.0, 2),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(schedule.next_change(2, 0.0), Some(900.0));
+        assert_eq!(schedule.next_change(1, 0.0), Some(300.0));
+        assert_eq!(schedule.next_change(1, 300.0), Some(600.0));
+        assert_eq!(schedule.next_change(0, 0.0), None);
+        assert_eq!(schedule.next_change(2, 900.0), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(StaffingSchedule::new(2, vec![shift(0.0, 300.0, 0)]).is_ok());
+        assert!(StaffingSchedule::new(2, vec![shift(0.0, 300.0, 3)]).is_err());
+        assert!(StaffingSchedule::new(2, vec![shift(300.0, 300.0, 1)]).is_err());
+        assert!(
+            StaffingSchedule::new(2, vec![shift(0.0, 300.0, 1), shift(200.0, 400.0, 1)]).is_err()
+        );
+    }
+}
@@ -1,21 +1,86 @@
-use crate::customer::Customer;
+use crate::balking::BalkingRule;
+use crate::breakdown::Breakdowns;
+use crate::class::CustomerClass;
+use crate::closing::ClosingMode;
+use crate::customer::{Customer, Outcome};
+use crate::distribution::ServiceDistribution;
 use crate::event::EventType;
-use crate::output::OutputMessage;
+use crate::lane::LaneOverflow;
+use crate::output::{OutputMessage, create_csv};
+use crate::queue::{QueueEntry, WaitingQueue};
+use crate::routing::{LaneLoad, WindowLanes};
+use crate::staffing::StaffingSchedule;
 use crate::statistics::Statistics;
+use crate::window::Window;
+use rand::rngs::StdRng;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
-use tokio::sync::mpsc;
+use std::sync::Arc;
+use tokio::sync::{Notify, mpsc};
+use tokio::task::AbortHandle;
 
 /// Holds the state of the simulation at any point in time
 pub struct SimState {
     pub customers: Vec<Customer>,
+    /// Customers waiting for a server, ordered by the queue discipline: one shared queue,
+    /// or one lane per window when `window_lanes` is set
+    pub waiting_queues: Vec<WaitingQueue>,
+    /// Notified when a customer joins the queue of the same index
+    pub queue_ready: Vec<Arc<Notify>>,
+    /// Total number of waiting customers
     pub waiting_queue_len: usize,
     pub busy_servers: usize,
     pub num_windows: usize,
     pub csv_file: Option<File>,
+    /// CSV file receiving one row per customer once the visit ends
+    pub customer_file: Option<File>,
     pub output_tx: Option<mpsc::UnboundedSender<OutputMessage>>,
     pub current_time: f64,
     pub stats: Statistics,
+    /// Seed used for random customer generation, echoed in reports and CSV output
+    pub seed: Option<u64>,
+    /// Random number generator for decisions made while the simulation runs (e.g. balking)
+    pub rng: StdRng,
+    /// Rule deciding whether arriving customers drive past a long queue
+    pub balking: Option<BalkingRule>,
+    /// Distribution of the time customers are willing to wait before leaving the queue
+    pub patience: Option<ServiceDistribution>,
+    /// Pending patience timers of waiting customers, cancelled when service starts
+    pub renege_timers: HashMap<usize, AbortHandle>,
+    /// Maximum number of cars waiting in the lane (None for unlimited)
+    pub lane_capacity: Option<usize>,
+    /// What happens to arriving cars when the lane is full
+    pub lane_overflow: LaneOverflow,
+    /// Notified whenever a car leaves the lane, waking a car blocked at the entrance
+    pub lane_space: Arc<Notify>,
+    /// Customer classes (empty when all customers are alike)
+    pub classes: Vec<CustomerClass>,
+    /// Number of waiting customers of each class
+    pub class_waiting: Vec<usize>,
+    /// Number of customers of each class being served
+    pub class_busy: Vec<usize>,
+    /// Routing and jockeying between the lanes when every window has its own lane
+    pub window_lanes: Option<WindowLanes>,
+    /// Speed and served classes of each window (empty when all windows are alike)
+    pub windows: Vec<Window>,
+    /// Customer at each window (only tracked with window lanes or window definitions)
+    pub window_customer: Vec<Option<usize>>,
+    /// Lane whose turn it is for round-robin routing
+    pub route_turn: usize,
+    /// Number of windows staffed over the day (all windows open when None)
+    pub staffing: Option<StaffingSchedule>,
+    /// Whether each window is open; a window due to close stays open until its current
+    /// customer is served
+    pub window_open: Vec<bool>,
+    /// Failure process shared by all windows without their own (no breakdowns when None)
+    pub breakdowns: Option<Breakdowns>,
+    /// Whether each window is out of service for a repair
+    pub window_down: Vec<bool>,
+    /// Whether the run stops at closing time or serves the customers still in line
+    pub closing: ClosingMode,
+    /// End of the warm-up period deleted from the statistics
+    pub warmup: Option<f64>,
 }
 
 impl SimState {
@@ -24,9 +89,381 @@ impl SimState {
     /// Should be called before any state change to properly track statistics
     pub fn update_integral(&mut self, now: f64) {
         self.stats.update_integrals(now, self.waiting_queue_len, self.busy_servers);
+        for (c, class) in self.stats.classes.iter_mut().enumerate() {
+            class
+                .stats
+                .update_integrals(now, self.class_waiting[c], self.class_busy[c]);
+        }
+        let lanes = self.window_lanes.is_some();
+        for (k, window) in self.stats.windows.iter_mut().enumerate() {
+            let waiting = if lanes {
+                self.waiting_queues[k].len()
+            } else {
+                0
+            };
+            let busy = usize::from(self.window_customer[k].is_some());
+            window.update_integrals(now, waiting, busy);
+        }
         self.current_time = now;
     }
 
+    /// Returns the number of open windows
+    pub fn open_windows(&self) -> usize {
+        self.window_open.iter().filter(|&&open| open).count()
+    }
+
+    /// Passes the open windows on to the statistics when staffing follows a schedule
+    pub fn track_open_windows(&mut self) {
+        if self.staffing.is_none() {
+            return;
+        }
+        let open = self.open_windows();
+        self.stats.set_open_windows(open);
+        for (window, &open) in self.stats.windows.iter_mut().zip(&self.window_open) {
+            window.set_open_windows(usize::from(open));
+        }
+    }
+
+    /// Opens or closes `window`, recording a `WindowOpen` or `WindowClose` event
+    pub fn set_window_open(&mut self, now: f64, window: usize, open: bool) {
+        self.update_integral(now);
+        self.window_open[window] = open;
+        self.track_open_windows();
+        let event = if open {
+            EventType::WindowOpen
+        } else {
+            EventType::WindowClose
+        };
+        self.record_window_event(now, event, window);
+    }
+
+    /// Returns true if `window` is open and not out of service for a repair
+    pub fn window_available(&self, window: usize) -> bool {
+        self.window_open[window] && !self.window_down[window]
+    }
+
+    /// Returns the failure process of `window`: its own or the one shared by all windows
+    pub fn breakdowns_of(&self, window: usize) -> Option<&Breakdowns> {
+        self.windows
+            .get(window)
+            .and_then(|w| w.breakdowns.as_ref())
+            .or(self.breakdowns.as_ref())
+    }
+
+    /// Draws the time after `now` at which `window` fails next (None without breakdowns)
+    pub fn next_failure(&mut self, window: usize, now: f64) -> Option<f64> {
+        let Self {
+            windows,
+            breakdowns,
+            rng,
+            ..
+        } = self;
+        let process = windows
+            .get(window)
+            .and_then(|w| w.breakdowns.as_ref())
+            .or(breakdowns.as_ref())?;
+        Some(now + process.time_between_failures.sample(rng))
+    }
+
+    /// Draws how long the repair of `window` takes
+    ///
+    /// # Panics
+    /// Panics if the window has no failure process
+    pub fn repair_time(&mut self, window: usize) -> f64 {
+        let repair = self
+            .breakdowns_of(window)
+            .expect("only windows with breakdowns are repaired")
+            .repair_time
+            .clone();
+        repair.sample(&mut self.rng)
+    }
+
+    /// Takes `window` out of service for a repair or back into service, recording a
+    /// `Breakdown` or `Repair` event
+    pub fn set_window_down(&mut self, now: f64, window: usize, down: bool) {
+        self.update_integral(now);
+        self.window_down[window] = down;
+        let count = self.window_down.iter().filter(|&&down| down).count();
+        let event = if down {
+            self.stats.record_breakdown(count);
+            if let Some(stats) = self.stats.windows.get_mut(window) {
+                stats.record_breakdown(1);
+            }
+            EventType::Breakdown
+        } else {
+            self.stats.set_down_windows(count);
+            if let Some(stats) = self.stats.windows.get_mut(window) {
+                stats.set_down_windows(0);
+            }
+            EventType::Repair
+        };
+        self.record_window_event(now, event, window);
+    }
+
+    /// Wakes every window once no customer is waiting and none will arrive any more, so that
+    /// closed windows stop waiting for their next shift
+    pub fn notify_if_drained(&self, arrivals_done: bool) {
+        if arrivals_done && self.waiting_queue_len == 0 {
+            for queue_ready in &self.queue_ready {
+                queue_ready.notify_waiters();
+            }
+        }
+    }
+
+    /// Returns the priority of a customer's class (0 without classes)
+    fn priority(&self, cust_id: usize) -> u32 {
+        self.classes
+            .get(self.customers[cust_id].class)
+            .map_or(0, |class| class.priority)
+    }
+
+    /// Returns the number of waiting customers customer `cust_id` sees on arrival, i.e. in the
+    /// shortest lane they may join when every window has its own lane
+    pub fn visible_queue_len(&self, cust_id: usize) -> usize {
+        match self.window_lanes {
+            Some(_) => (0..self.waiting_queues.len())
+                .filter(|&k| self.serves(k, cust_id))
+                .map(|k| self.waiting_queues[k].len())
+                .min()
+                .unwrap_or(0),
+            None => self.waiting_queue_len,
+        }
+    }
+
+    /// Returns true if customer `cust_id` fits in the lane (in any lane whose window serves
+    /// them with window lanes)
+    pub fn lane_has_room(&self, cust_id: usize) -> bool {
+        match (self.lane_capacity, &self.window_lanes) {
+            (None, _) => true,
+            (Some(_), Some(_)) => {
+                (0..self.waiting_queues.len()).any(|k| self.lane_load(k, cust_id).open)
+            }
+            (Some(capacity), None) => self.waiting_queue_len < capacity,
+        }
+    }
+
+    /// Returns true if `window` serves customer `cust_id`
+    pub fn serves(&self, window: usize, cust_id: usize) -> bool {
+        self.windows
+            .get(window)
+            .is_none_or(|w| w.serves(self.classes.get(self.customers[cust_id].class)))
+    }
+
+    /// Returns how long `window` takes to serve customer `cust_id`
+    pub fn service_time(&self, window: usize, cust_id: usize) -> f64 {
+        let speed = self.windows.get(window).map_or(1.0, |w| w.speed);
+        self.customers[cust_id].service_duration / speed
+    }
+
+    /// Returns the cars in a window's lane, the service time left for them and whether
+    /// customer `cust_id` can join the lane
+    fn lane_load(&self, window: usize, cust_id: usize) -> LaneLoad {
+        let queue = &self.waiting_queues[window];
+        let mut cars = queue.len();
+        let mut work: f64 = queue
+            .iter()
+            .map(|e| self.service_time(window, e.cust_id))
+            .sum();
+        if let Some(id) = self.window_customer[window] {
+            let start = self.customers[id]
+                .service_start_time
+                .unwrap_or(self.current_time);
+            cars += 1;
+            work += (start + self.service_time(window, id) - self.current_time).max(0.0);
+        }
+        LaneLoad {
+            cars,
+            work,
+            open: self
+                .lane_capacity
+                .is_none_or(|capacity| queue.len() < capacity)
+                && self.serves(window, cust_id),
+        }
+    }
+
+    /// Adds a customer to the waiting queue, choosing a lane by the routing policy when
+    /// every window has its own lane
+    ///
+    /// # Panics
+    /// Panics if no lane has room
+    pub fn enqueue(&mut self, cust_id: usize) {
+        let queue = match self.window_lanes.as_ref().map(|lanes| lanes.routing) {
+            Some(routing) => {
+                let mut loads: Vec<LaneLoad> = (0..self.waiting_queues.len())
+                    .map(|k| self.lane_load(k, cust_id))
+                    .collect();
+                // Lanes of closed or broken down windows are only joined when no available
+                // window serves the car
+                if (0..loads.len()).any(|k| loads[k].open && self.window_available(k)) {
+                    for (k, load) in loads.iter_mut().enumerate() {
+                        load.open &= self.window_available(k);
+                    }
+                }
+                let lane = routing
+                    .select(&mut self.rng, &loads, self.route_turn)
+                    .expect("an arriving car needs an open lane");
+                self.route_turn = (lane + 1) % loads.len();
+                self.customers[cust_id].window = Some(lane);
+                lane
+            }
+            None => 0,
+        };
+        let entry = QueueEntry {
+            cust_id,
+            priority: self.priority(cust_id),
+            service_duration: self.customers[cust_id].service_duration,
+        };
+        self.waiting_queues[queue].push(entry);
+        // Wake every idle window, as only some of them may serve the customer
+        self.queue_ready[queue].notify_waiters();
+        self.waiting_queue_len += 1;
+        if !self.classes.is_empty() {
+            self.class_waiting[self.customers[cust_id].class] += 1;
+        }
+    }
+
+    /// Takes the next customer `window` serves
+    pub fn next_customer(&mut self, window: usize) -> Option<usize> {
+        let queue = if self.window_lanes.is_some() {
+            window
+        } else {
+            0
+        };
+        let Self {
+            waiting_queues,
+            rng,
+            customers,
+            classes,
+            windows,
+            ..
+        } = self;
+        let Some(spec) = windows.get(window) else {
+            return waiting_queues[queue].pop(rng).map(|e| e.cust_id);
+        };
+        waiting_queues[queue]
+            .pop_where(rng, |e| {
+                spec.serves(classes.get(customers[e.cust_id].class))
+            })
+            .map(|e| e.cust_id)
+    }
+
+    /// Moves waiting cars to an adjacent lane with at least `jockey_threshold` fewer cars
+    /// until the lanes are balanced, recording a `Jockey` event for every move
+    pub fn rebalance(&mut self, now: f64) {
+        let Some(threshold) = self.window_lanes.as_ref().and_then(|l| l.jockey_threshold) else {
+            return;
+        };
+        let lanes = self.waiting_queues.len();
+        loop {
+            let cars: Vec<usize> = (0..lanes)
+                .map(|k| {
+                    self.waiting_queues[k].len() + usize::from(self.window_customer[k].is_some())
+                })
+                .collect();
+            // Always even out the most unbalanced pair first; the last car in a lane only
+            // moves to a window that serves it
+            let Some((from, to)) = (1..lanes)
+                .flat_map(|k| [(k - 1, k), (k, k - 1)])
+                .filter(|&(from, to)| {
+                    cars[from] >= cars[to] + threshold
+                        && self.window_available(to)
+                        && self.waiting_queues[from]
+                            .last()
+                            .is_some_and(|e| self.serves(to, e.cust_id))
+                })
+                .max_by_key(|&(from, to)| cars[from] - cars[to])
+            else {
+                break;
+            };
+
+            self.update_integral(now);
+            let entry = self.waiting_queues[from]
+                .pop_last()
+                .expect("the lane has a waiting car");
+            self.waiting_queues[to].push(entry);
+            self.customers[entry.cust_id].window = Some(to);
+            self.record_history(now, EventType::Jockey, entry.cust_id);
+            self.queue_ready[to].notify_waiters();
+        }
+    }
+
+    /// Updates the per-class counters when a waiting customer starts service (`to_service`)
+    /// or leaves the queue
+    pub fn leave_queue(&mut self, cust_id: usize, to_service: bool) {
+        if self.classes.is_empty() {
+            return;
+        }
+        let class = self.customers[cust_id].class;
+        self.class_waiting[class] -= 1;
+        if to_service {
+            self.class_busy[class] += 1;
+        }
+    }
+
+    /// Updates the per-class counters when a customer finishes service
+    pub fn finish_service(&mut self, cust_id: usize) {
+        if !self.classes.is_empty() {
+            self.class_busy[self.customers[cust_id].class] -= 1;
+        }
+    }
+
+    /// Removes a customer who ran out of patience from the queue
+    ///
+    /// Does nothing if the customer is no longer waiting (e.g. already being served).
+    pub fn renege(&mut self, now: f64, cust_id: usize) {
+        self.renege_timers.remove(&cust_id);
+        let queue = self.customers[cust_id].window.unwrap_or(0);
+        if self.waiting_queues[queue]
+            .iter()
+            .any(|e| e.cust_id == cust_id)
+        {
+            self.update_integral(now);
+            self.waiting_queues[queue].remove(cust_id);
+            self.waiting_queue_len -= 1;
+            self.leave_queue(cust_id, false);
+            self.record_history(now, EventType::Renege, cust_id);
+            self.lane_space.notify_one();
+            self.rebalance(now);
+        }
+    }
+
+    /// Records an event of `window` itself (e.g. `WindowOpen`) in the simulation history and
+    /// sends it to the output thread
+    pub fn record_window_event(&mut self, now: f64, event: EventType, window: usize) {
+        if let Some(ref tx) = self.output_tx {
+            let msg = OutputMessage {
+                time: now,
+                event,
+                cust_id: None,
+                queue_len: self.waiting_queue_len,
+                busy_servers: self.busy_servers,
+                num_windows: self.open_windows(),
+                station: None,
+                window: Some(window),
+                class: None,
+            };
+            let _ = tx.send(msg);
+        }
+
+        if let Some(ref mut file) = self.csv_file {
+            let _ = write!(
+                file,
+                "{:.2},{},,{},{},{}",
+                now,
+                event,
+                self.waiting_queue_len,
+                self.busy_servers,
+                window + 1
+            );
+            let _ = if self.classes.is_empty() {
+                writeln!(file)
+            } else {
+                writeln!(file, ",")
+            };
+            let _ = file.flush();
+        }
+    }
+
     /// Records an event in the simulation history and sends it to the output thread
     /// Also streams the event to CSV file if one is open
     pub fn record_history(&mut self, now: f64, event: EventType, cust_id: usize) {
@@ -35,49 +472,85 @@ impl SimState {
             let msg = OutputMessage {
                 time: now,
                 event,
-                cust_id,
+                cust_id: Some(cust_id),
                 queue_len: self.waiting_queue_len,
                 busy_servers: self.busy_servers,
-                num_windows: self.num_windows,
+                num_windows: self.open_windows(),
+                station: None,
+                window: self.customers.get(cust_id).and_then(|c| c.window),
+                class: (!self.classes.is_empty()).then(|| self.customers[cust_id].class),
             };
             let _ = tx.send(msg);
         }
 
         // Stream to CSV file if open
         if let Some(ref mut file) = self.csv_file {
-            let _ = writeln!(
+            let _ = write!(
                 file,
                 "{:.2},{},{},{},{}",
                 now, event, cust_id, self.waiting_queue_len, self.busy_servers
             );
+            if !self.stats.windows.is_empty() {
+                let _ = match self.customers[cust_id].window {
+                    Some(k) => write!(file, ",{}", k + 1),
+                    None => write!(file, ","),
+                };
+            }
+            let _ = match self.classes.get(self.customers[cust_id].class) {
+                Some(class) => writeln!(file, ",{}", class.name),
+                None => writeln!(file),
+            };
             // Flush CSV file too for real-time streaming
             let _ = file.flush();
         }
 
         // Track max queue length
         self.stats.update_max_queue(self.waiting_queue_len);
+        self.stats
+            .update_segment_max_queue(now, self.waiting_queue_len);
 
-        // Update running statistics based on event type
-        if let EventType::ServiceEnd = event
-            && cust_id < self.customers.len()
-            && let (Some(start), Some(end)) = (
-                self.customers[cust_id].service_start_time,
-                self.customers[cust_id].service_end_time,
-            )
+        let outcome = match event {
+            EventType::ServiceEnd => Some(Outcome::Served),
+            EventType::Balk => Some(Outcome::Balked),
+            EventType::Reject => Some(Outcome::Rejected),
+            EventType::Renege => Some(Outcome::Reneged),
+            _ => None,
+        };
+        if let Some(outcome) = outcome
+            && let Some(customer) = self.customers.get_mut(cust_id)
         {
-            let arrival = self.customers[cust_id].arrival_time;
-            let wait_time = start - arrival;
-            let service_time = end - start;
+            customer.outcome = Some(outcome);
+            self.write_customer_row(now, cust_id, outcome.name());
+        }
 
-            self.stats.record_completion(wait_time, service_time);
+        record_event(&mut self.stats, &self.customers, now, event, cust_id);
+        if !self.classes.is_empty() && cust_id < self.customers.len() {
+            let class = self.customers[cust_id].class;
+            let waiting = self.class_waiting[class];
+            let class_stats = &mut self.stats.classes[class].stats;
+            class_stats.update_max_queue(waiting);
+            record_event(class_stats, &self.customers, now, event, cust_id);
+        }
+        if let Some(window) = self.customers.get(cust_id).and_then(|c| c.window)
+            && let Some(window_stats) = self.stats.windows.get_mut(window)
+        {
+            if self.window_lanes.is_some() {
+                window_stats.update_max_queue(self.waiting_queues[window].len());
+            }
+            record_event(window_stats, &self.customers, now, event, cust_id);
         }
     }
 
     /// Initialize CSV file for streaming events
     pub fn init_csv(&mut self, filename: &str) -> std::io::Result<()> {
-        let mut file = File::create(filename)?;
-        writeln!(file, "Time,Event,CustomerID,QueueLength,BusyServers")?;
-        self.csv_file = Some(file);
+        let mut header = String::from("Time,Event,CustomerID,QueueLength,BusyServers");
+        if !self.stats.windows.is_empty() {
+            header.push_str(",Window");
+        }
+        if !self.classes.is_empty() {
+            header.push_str(",Class");
+        }
+        self.csv_file = Some(create_csv(filename, self.seed, &header)?);
         Ok(())
     }
 
@@ -87,4 +560,133 @@ impl SimState {
             let _ = file.flush();
         }
     }
+
+    /// Initialize the CSV file receiving one row per customer
+    pub fn init_customer_csv(&mut self, filename: &str) -> std::io::Result<()> {
+        self.customer_file = Some(create_csv(
+            filename,
+            self.seed,
+            "CustomerID,Class,Arrival,ServiceStart,ServiceEnd,Wait,Service,Sojourn,Outcome",
+        )?);
+        Ok(())
+    }
+
+    /// Writes the row of customer `cust_id`, whose visit ended at `now` with `outcome`
+    ///
+    /// Times that do not apply to the outcome (e.g. the service of a customer who balked)
+    /// are left empty; customers still in the system have their times up to `now`.
+    fn write_customer_row(&mut self, now: f64, cust_id: usize, outcome: &str) {
+        let Some(file) = self.customer_file.as_mut() else {
+            return;
+        };
+        let customer = &self.customers[cust_id];
+        let class = self
+            .classes
+            .get(customer.class)
+            .map_or("", |class| class.name.as_str());
+        let time = |t: Option<f64>| t.map_or(String::new(), |t| format!("{:.2}", t));
+        let arrival = customer.arrival_time;
+        let start = customer.service_start_time;
+        let end = customer
+            .service_end_time
+            .filter(|_| customer.outcome == Some(Outcome::Served));
+        // Customers who balked or were turned away leave on arrival
+        let left = match customer.outcome {
+            Some(Outcome::Balked | Outcome::Rejected) => arrival,
+            _ => end.unwrap_or(now),
+        };
+        let wait = start.unwrap_or(left) - arrival;
+        let _ = writeln!(
+            file,
+            "{},{},{:.2},{},{},{:.2},{},{:.2},{}",
+            cust_id,
+            class,
+            arrival,
+            time(start),
+            time(end),
+            wait,
+            time(end.zip(start).map(|(end, start)| end - start)),
+            left - arrival,
+            outcome
+        );
+        let _ = file.flush();
+    }
+
+    /// Writes the rows of the customers still waiting or at a window at `now` and closes
+    /// the customer CSV file
+    pub fn close_customer_csv(&mut self, now: f64) {
+        if self.customer_file.is_none() {
+            return;
+        }
+        let mut in_system: Vec<usize> = self
+            .waiting_queues
+            .iter()
+            .flat_map(|queue| queue.iter().map(|entry| entry.cust_id))
+            .chain(
+                self.customers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| c.outcome.is_none() && c.service_start_time.is_some())
+                    .map(|(i, _)| i),
+            )
+            .collect();
+        in_system.sort_unstable();
+        for cust_id in in_system {
+            self.write_customer_row(now, cust_id, "in_system");
+        }
+        if let Some(mut file) = self.customer_file.take() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Updates `stats` with an event of customer `cust_id`
+fn record_event(
+    stats: &mut Statistics,
+    customers: &[Customer],
+    now: f64,
+    event: EventType,
+    cust_id: usize,
+) {
+    let Some(customer) = customers.get(cust_id) else {
+        return;
+    };
+    let arrival = customer.arrival_time;
+
+    // Update running statistics based on event type
+    match event {
+        EventType::Arrival => stats.record_arrival(arrival),
+        EventType::Balk => {
+            stats.record_arrival(arrival);
+            stats.record_balk(arrival);
+        }
+        EventType::Reject => {
+            stats.record_arrival(arrival);
+            stats.record_reject(arrival);
+        }
+        EventType::Renege => {
+            let entry = customer.queue_entry_time.unwrap_or(arrival);
+            stats.record_renege(arrival, now - entry);
+            stats.record_bucket_renege(now);
+        }
+        EventType::ServiceEnd => {
+            if let (Some(start), Some(end)) =
+                (customer.service_start_time, customer.service_end_time)
+            {
+                let wait_time = start - arrival;
+                let service_time = end - start;
+
+                stats.record_completion(wait_time, service_time);
+                stats.record_segment_completion(arrival, wait_time);
+                stats.record_bucket_completion(now, wait_time);
+            }
+        }
+        EventType::Jockey => stats.jockeyed_customers += 1,
+        EventType::ServiceStart
+        | EventType::Blocked
+        | EventType::WindowOpen
+        | EventType::WindowClose
+        | EventType::Breakdown
+        | EventType::Repair => {}
+    }
 }
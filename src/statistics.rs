@@ -1,23 +1,176 @@
+use crate::analytics::{BatchMeans, QueueingModel};
+use crate::cost::{CostModel, CostSummary};
 use crate::duration::format_duration;
+use crate::histogram::{Histogram, HistogramBins, QuantileSketch};
+use crate::output::create_csv;
+use crate::timeseries::TimeSeries;
+use crate::warmup::{QueueLengthSeries, mser5};
+use std::io::{self, Write};
+
+/// Statistics restricted to one period of the simulation (e.g. a lunch rush)
+///
+/// Customers are attributed to the segment in which they arrived; time-weighted
+/// integrals only cover the part of the run that falls within the segment.
+#[derive(Debug, Clone)]
+pub struct SegmentStatistics {
+    pub label: String,
+    pub from: f64,
+    pub to: f64,
+    pub arrivals: usize,
+    pub balked_customers: usize,
+    pub reneged_customers: usize,
+    pub rejected_customers: usize,
+    pub completed_customers: usize,
+    pub total_wait_time: f64,
+    pub max_wait_time: f64,
+    pub queue_length_integral: f64,
+    pub server_busy_integral: f64,
+    /// Time-weighted number of open windows, tracked when staffing follows a schedule
+    pub capacity_integral: f64,
+    pub max_queue_length: usize,
+}
+
+impl SegmentStatistics {
+    /// Creates an empty tracker for the period `[from, to)`
+    #[must_use]
+    pub fn new(label: String, from: f64, to: f64) -> Self {
+        Self {
+            label,
+            from,
+            to,
+            arrivals: 0,
+            balked_customers: 0,
+            reneged_customers: 0,
+            rejected_customers: 0,
+            completed_customers: 0,
+            total_wait_time: 0.0,
+            max_wait_time: 0.0,
+            queue_length_integral: 0.0,
+            server_busy_integral: 0.0,
+            capacity_integral: 0.0,
+            max_queue_length: 0,
+        }
+    }
+
+    /// Returns true if `time` falls within the segment
+    pub fn contains(&self, time: f64) -> bool {
+        time >= self.from && time < self.to
+    }
+
+    /// Returns how much of the interval `[start, end]` overlaps the segment
+    fn overlap(&self, start: f64, end: f64) -> f64 {
+        (end.min(self.to) - start.max(self.from)).max(0.0)
+    }
+}
+
+/// Snapshot of the statistics at closing time, telling the open hours from the overtime
+/// needed to serve the customers still in line
+#[derive(Debug, Clone)]
+pub struct ClosingStatistics {
+    pub time: f64,
+    /// Customers waiting or at a window at closing time
+    pub in_system: usize,
+    pub completed_customers: usize,
+    pub total_wait_time: f64,
+    pub queue_length_integral: f64,
+    pub server_busy_integral: f64,
+    pub capacity_integral: f64,
+}
+
+/// Statistics restricted to the customers of one class
+#[derive(Debug)]
+pub struct ClassStatistics {
+    pub name: String,
+    pub priority: u32,
+    /// Queue length and busy servers count only customers of this class
+    pub stats: Statistics,
+}
 
 /// Tracks running statistics for the simulation
 #[derive(Debug)]
 pub struct Statistics {
     // Running totals
+    pub arrivals: usize,
     pub total_wait_time: f64,
     pub total_service_time: f64,
+    /// Time in system (waiting plus service) of the completed customers
+    pub total_system_time: f64,
     pub completed_customers: usize,
+    pub balked_customers: usize,
+    pub reneged_customers: usize,
+    pub total_time_to_abandon: f64,
+    pub rejected_customers: usize,
+    pub blocked_customers: usize,
+    pub total_blocked_time: f64,
+    /// Moves of waiting customers to a shorter lane (into the lane, for per-window statistics)
+    pub jockeyed_customers: usize,
+    /// Window failures (of the window itself, for per-window statistics)
+    pub breakdowns: usize,
 
     // Time-weighted integrals
     pub queue_length_integral: f64,
     pub server_busy_integral: f64,
+    /// Time during which the lane was at capacity
+    pub lane_full_integral: f64,
+    pub lane_capacity: Option<usize>,
+    /// Windows open at the moment (None unless staffing follows a schedule)
+    pub open_windows: Option<usize>,
+    /// Time-weighted number of open windows, the capacity utilization is measured against
+    pub capacity_integral: f64,
+    /// Windows out of service for repair at the moment
+    pub down_windows: usize,
+    /// Time-weighted number of windows out of service for repair
+    pub downtime_integral: f64,
 
     // Peak values
     pub max_wait_time: f64,
+    pub max_system_time: f64,
     pub max_queue_length: usize,
 
+    // Distributions of the times of completed customers
+    pub wait_times: QuantileSketch,
+    pub service_times: QuantileSketch,
+    /// Waiting plus service time
+    pub system_times: QuantileSketch,
+    /// Histograms of the waiting time and the time in system (None unless bins are set)
+    pub wait_histogram: Option<Histogram>,
+    pub system_histogram: Option<Histogram>,
+    /// Batch means of the waiting time and the time in system, in order of completion
+    pub wait_batches: BatchMeans,
+    pub system_batches: BatchMeans,
+    /// Model whose steady state the report compares with (None if no model applies)
+    pub queueing_model: Option<QueueingModel>,
+    /// Prices of the cost and revenue report (None if no costs are configured)
+    pub cost_model: Option<CostModel>,
+    /// Waiting time beyond the cost model's threshold, summed over the served customers
+    pub excess_wait_time: f64,
+
     // Tracking state
     pub last_event_time: f64,
+    /// Start of the observed period, later than 0 once a warm-up period was deleted
+    pub observation_start: f64,
+    /// Customers waiting or at a window at the end of the warm-up period
+    pub warmup_in_system: usize,
+    /// Queue length over time for the warm-up detection (None unless enabled)
+    pub queue_length_series: Option<QueueLengthSeries>,
+    /// Statistics per time bucket over the whole run, warm-up included (None unless enabled)
+    pub time_series: Option<TimeSeries>,
+
+    // Per-period breakdown (empty unless segments are registered)
+    pub segments: Vec<SegmentStatistics>,
+
+    // Per-class breakdown (empty unless customer classes are registered)
+    pub classes: Vec<ClassStatistics>,
+
+    // Per-window breakdown (empty unless windows are defined or have their own lanes); queue
+    // length and busy servers count only the window's lane and the window itself
+    pub windows: Vec<Statistics>,
+    pub window_labels: Vec<String>,
+    /// Whether every window has its own lane
+    pub window_lanes: bool,
+
+    /// Statistics at closing time when the lane is drained afterwards
+    pub closing: Option<ClosingStatistics>,
 }
 
 impl Statistics {
@@ -25,36 +178,347 @@ impl Statistics {
     #[must_use]
     pub fn new() -> Self {
         Self {
+            arrivals: 0,
             total_wait_time: 0.0,
             total_service_time: 0.0,
+            total_system_time: 0.0,
             completed_customers: 0,
+            balked_customers: 0,
+            reneged_customers: 0,
+            total_time_to_abandon: 0.0,
+            rejected_customers: 0,
+            blocked_customers: 0,
+            total_blocked_time: 0.0,
+            jockeyed_customers: 0,
+            breakdowns: 0,
             queue_length_integral: 0.0,
             server_busy_integral: 0.0,
+            lane_full_integral: 0.0,
+            lane_capacity: None,
+            open_windows: None,
+            capacity_integral: 0.0,
+            down_windows: 0,
+            downtime_integral: 0.0,
             max_wait_time: 0.0,
+            max_system_time: 0.0,
             max_queue_length: 0,
+            wait_times: QuantileSketch::new(),
+            service_times: QuantileSketch::new(),
+            system_times: QuantileSketch::new(),
+            wait_histogram: None,
+            system_histogram: None,
+            wait_batches: BatchMeans::new(),
+            system_batches: BatchMeans::new(),
+            queueing_model: None,
+            cost_model: None,
+            excess_wait_time: 0.0,
             last_event_time: 0.0,
+            observation_start: 0.0,
+            warmup_in_system: 0,
+            queue_length_series: None,
+            time_series: None,
+            segments: Vec::new(),
+            classes: Vec::new(),
+            windows: Vec::new(),
+            window_labels: Vec::new(),
+            window_lanes: false,
+            closing: None,
         }
     }
 
+    /// Enables tracking of the time the lane spends at `capacity`
+    pub fn set_lane_capacity(&mut self, capacity: usize) {
+        self.lane_capacity = Some(capacity);
+    }
+
+    /// Sets the number of windows open from now on, measuring utilization against the
+    /// windows open over time instead of a fixed number of windows
+    pub fn set_open_windows(&mut self, open: usize) {
+        self.open_windows = Some(open);
+    }
+
+    /// Records a window failure, leaving `down` windows out of service
+    pub fn record_breakdown(&mut self, down: usize) {
+        self.breakdowns += 1;
+        self.down_windows = down;
+    }
+
+    /// Sets the number of windows out of service from now on
+    pub fn set_down_windows(&mut self, down: usize) {
+        self.down_windows = down;
+    }
+
+    /// Takes a snapshot at closing time `time`, with `in_system` customers left to serve
+    ///
+    /// The integrals must be up to date (see [`Statistics::update_integrals`]).
+    pub fn record_closing(&mut self, time: f64, in_system: usize) {
+        self.closing = Some(ClosingStatistics {
+            time,
+            in_system,
+            completed_customers: self.completed_customers,
+            total_wait_time: self.total_wait_time,
+            queue_length_integral: self.queue_length_integral,
+            server_busy_integral: self.server_busy_integral,
+            capacity_integral: self.capacity_integral,
+        });
+    }
+
+    /// Counts the waiting times and the times in system of completed customers in `bins`
+    pub fn set_histogram(&mut self, bins: HistogramBins) {
+        self.wait_histogram = Some(Histogram::new(bins));
+        self.system_histogram = Some(Histogram::new(bins));
+    }
+
+    /// Records the queue length over intervals of width `interval` to detect the warm-up
+    /// period (see [`mser5`])
+    pub fn track_queue_length(&mut self, interval: f64) {
+        self.queue_length_series = Some(QueueLengthSeries::new(interval));
+    }
+
+    /// Tracks arrivals, completions, queue length, utilization and waits per time bucket of
+    /// width `interval`
+    pub fn set_time_series(&mut self, interval: f64) {
+        self.time_series = Some(TimeSeries::new(interval));
+    }
+
+    /// Deletes the warm-up period ending at `now`, with `in_system` customers waiting or
+    /// at a window
+    ///
+    /// Counts, integrals and maxima start over (also per segment, class and window), while
+    /// the configuration of the tracking, the queue length series and the time series are
+    /// kept. The
+    /// integrals must be up to date (see [`Statistics::update_integrals`]).
+    pub fn reset(&mut self, now: f64, in_system: usize) {
+        let queue_length_series = self.queue_length_series.take();
+        let time_series = self.time_series.take();
+        *self = Self {
+            warmup_in_system: in_system,
+            queue_length_series,
+            time_series,
+            ..self.cleared(now)
+        };
+    }
+
+    /// Returns empty statistics tracking the same things as these, starting at `now`
+    fn cleared(&self, now: f64) -> Self {
+        Self {
+            lane_capacity: self.lane_capacity,
+            open_windows: self.open_windows,
+            down_windows: self.down_windows,
+            last_event_time: now,
+            observation_start: now,
+            wait_histogram: self
+                .wait_histogram
+                .as_ref()
+                .map(|h| Histogram::new(h.bins())),
+            system_histogram: self
+                .system_histogram
+                .as_ref()
+                .map(|h| Histogram::new(h.bins())),
+            queueing_model: self.queueing_model.clone(),
+            cost_model: self.cost_model.clone(),
+            segments: self
+                .segments
+                .iter()
+                .map(|s| SegmentStatistics::new(s.label.clone(), s.from, s.to))
+                .collect(),
+            classes: self
+                .classes
+                .iter()
+                .map(|c| ClassStatistics {
+                    name: c.name.clone(),
+                    priority: c.priority,
+                    stats: c.stats.cleared(now),
+                })
+                .collect(),
+            windows: self.windows.iter().map(|w| w.cleared(now)).collect(),
+            window_labels: self.window_labels.clone(),
+            window_lanes: self.window_lanes,
+            ..Self::new()
+        }
+    }
+
+    /// Returns the window time available over `observed` seconds: the time-weighted open
+    /// windows when tracked, otherwise `num_windows` windows open all the time
+    fn capacity(&self, observed: f64, num_windows: usize) -> f64 {
+        match self.open_windows {
+            Some(_) => self.capacity_integral,
+            None => observed * num_windows as f64,
+        }
+    }
+
+    /// Replaces the tracked periods with empty statistics for the given segments
+    pub fn set_segments(&mut self, segments: Vec<SegmentStatistics>) {
+        self.segments = segments;
+    }
+
+    /// Replaces the tracked classes with empty statistics for the given classes
+    pub fn set_classes(&mut self, classes: Vec<ClassStatistics>) {
+        self.classes = classes;
+    }
+
+    /// Replaces the tracked windows with empty statistics for windows with the given labels
+    pub fn set_windows(&mut self, labels: Vec<String>, window_lanes: bool) {
+        self.windows = labels.iter().map(|_| Statistics::new()).collect();
+        self.window_labels = labels;
+        self.window_lanes = window_lanes;
+    }
+
     /// Updates the time-weighted integrals
     pub fn update_integrals(&mut self, now: f64, queue_len: usize, busy_servers: usize) {
         let time_passed = now - self.last_event_time;
         if time_passed > 0.0 {
             self.queue_length_integral += time_passed * queue_len as f64;
             self.server_busy_integral += time_passed * busy_servers as f64;
+            if self
+                .lane_capacity
+                .is_some_and(|capacity| queue_len >= capacity)
+            {
+                self.lane_full_integral += time_passed;
+            }
+            let open_windows = self.open_windows.unwrap_or(0);
+            self.capacity_integral += time_passed * open_windows as f64;
+            self.downtime_integral += time_passed * self.down_windows as f64;
+            if let Some(series) = &mut self.queue_length_series {
+                series.record(self.last_event_time, now, queue_len);
+            }
+            if let Some(series) = &mut self.time_series {
+                series.record_integrals(
+                    self.last_event_time,
+                    now,
+                    queue_len,
+                    busy_servers,
+                    open_windows,
+                );
+            }
+            for segment in &mut self.segments {
+                let overlap = segment.overlap(self.last_event_time, now);
+                segment.queue_length_integral += overlap * queue_len as f64;
+                segment.server_busy_integral += overlap * busy_servers as f64;
+                segment.capacity_integral += overlap * open_windows as f64;
+            }
             self.last_event_time = now;
         }
     }
 
+    /// Records a customer arrival, also in the segment and the time bucket it falls into
+    pub fn record_arrival(&mut self, arrival_time: f64) {
+        self.arrivals += 1;
+        if let Some(series) = &mut self.time_series {
+            series.record_arrival(arrival_time);
+        }
+        if let Some(segment) = self.segments.iter_mut().find(|s| s.contains(arrival_time)) {
+            segment.arrivals += 1;
+        }
+    }
+
+    /// Records a customer who balked (drove past without joining the queue)
+    pub fn record_balk(&mut self, arrival_time: f64) {
+        self.balked_customers += 1;
+        if let Some(series) = &mut self.time_series {
+            series.record_balk(arrival_time);
+        }
+        if let Some(segment) = self.segments.iter_mut().find(|s| s.contains(arrival_time)) {
+            segment.balked_customers += 1;
+        }
+    }
+
+    /// Records a customer turned away because the lane was full
+    pub fn record_reject(&mut self, arrival_time: f64) {
+        self.rejected_customers += 1;
+        if let Some(series) = &mut self.time_series {
+            series.record_reject(arrival_time);
+        }
+        if let Some(segment) = self.segments.iter_mut().find(|s| s.contains(arrival_time)) {
+            segment.rejected_customers += 1;
+        }
+    }
+
+    /// Records a customer who had to wait `blocked_time` on the street before entering the lane
+    pub fn record_blocked(&mut self, blocked_time: f64) {
+        self.blocked_customers += 1;
+        self.total_blocked_time += blocked_time;
+    }
+
+    /// Records a customer who left the queue (reneged) after waiting `waited` seconds in it
+    ///
+    /// Customers who arrived during the warm-up period are not counted, so that the
+    /// abandonment rate relates the reneges to the arrivals after it.
+    pub fn record_renege(&mut self, arrival_time: f64, waited: f64) {
+        if arrival_time < self.observation_start {
+            return;
+        }
+        self.reneged_customers += 1;
+        self.total_time_to_abandon += waited;
+        if let Some(segment) = self.segments.iter_mut().find(|s| s.contains(arrival_time)) {
+            segment.reneged_customers += 1;
+        }
+    }
+
+    /// Records a completed customer in the segment in which it arrived
+    pub fn record_segment_completion(&mut self, arrival_time: f64, wait_time: f64) {
+        if let Some(segment) = self.segments.iter_mut().find(|s| s.contains(arrival_time)) {
+            segment.completed_customers += 1;
+            segment.total_wait_time += wait_time;
+            if wait_time > segment.max_wait_time {
+                segment.max_wait_time = wait_time;
+            }
+        }
+    }
+
+    /// Records a customer whose service ended at `now` after waiting `wait_time` in the
+    /// time bucket containing `now`
+    pub fn record_bucket_completion(&mut self, now: f64, wait_time: f64) {
+        if let Some(series) = &mut self.time_series {
+            series.record_completion(now, wait_time);
+        }
+    }
+
+    /// Records a customer who left the queue at `now` in the time bucket containing `now`
+    pub fn record_bucket_renege(&mut self, now: f64) {
+        if let Some(series) = &mut self.time_series {
+            series.record_renege(now);
+        }
+    }
+
+    /// Updates the maximum queue length of the segment and the time bucket containing `now`
+    pub fn update_segment_max_queue(&mut self, now: f64, current_queue_len: usize) {
+        if let Some(series) = &mut self.time_series {
+            series.update_max_queue(now, current_queue_len);
+        }
+        if let Some(segment) = self.segments.iter_mut().find(|s| s.contains(now))
+            && current_queue_len > segment.max_queue_length
+        {
+            segment.max_queue_length = current_queue_len;
+        }
+    }
+
     /// Records a completed customer's statistics
     pub fn record_completion(&mut self, wait_time: f64, service_time: f64) {
         self.total_wait_time += wait_time;
         self.total_service_time += service_time;
+        self.total_system_time += wait_time + service_time;
         self.completed_customers += 1;
 
         if wait_time > self.max_wait_time {
             self.max_wait_time = wait_time;
         }
+        self.max_system_time = self.max_system_time.max(wait_time + service_time);
+
+        self.wait_times.record(wait_time);
+        self.service_times.record(service_time);
+        self.system_times.record(wait_time + service_time);
+        self.wait_batches.record(wait_time);
+        self.system_batches.record(wait_time + service_time);
+        if let Some(histogram) = &mut self.wait_histogram {
+            histogram.record(wait_time);
+        }
+        if let Some(costs) = &self.cost_model {
+            self.excess_wait_time += costs.excess_wait(wait_time);
+        }
+        if let Some(histogram) = &mut self.system_histogram {
+            histogram.record(wait_time + service_time);
+        }
     }
 
     /// Updates the maximum queue length if current exceeds it
@@ -64,12 +528,151 @@ impl Statistics {
         }
     }
 
+    /// Returns the key performance measures of the run by name, to compare runs
+    ///
+    /// Times are in seconds; averages are 0 when nothing was observed.
+    pub fn metrics(&self, current_time: f64, num_windows: usize) -> Vec<(&'static str, f64)> {
+        let observed = current_time - self.observation_start;
+        let per_customer = |total: f64| {
+            if self.completed_customers > 0 {
+                total / self.completed_customers as f64
+            } else {
+                0.0
+            }
+        };
+        let per_time = |integral: f64| {
+            if observed > 0.0 {
+                integral / observed
+            } else {
+                0.0
+            }
+        };
+        let capacity = self.capacity(observed, num_windows);
+        let utilization = if capacity > 0.0 {
+            self.server_busy_integral / capacity * 100.0
+        } else {
+            0.0
+        };
+
+        let mut metrics = vec![
+            ("Arrivals", self.arrivals as f64),
+            ("Customers completed", self.completed_customers as f64),
+            ("Customers balked", self.balked_customers as f64),
+            ("Customers reneged", self.reneged_customers as f64),
+            ("Customers rejected", self.rejected_customers as f64),
+            ("Customers blocked", self.blocked_customers as f64),
+            ("Lane changes", self.jockeyed_customers as f64),
+            ("Window breakdowns", self.breakdowns as f64),
+            (
+                "Average waiting time (s)",
+                per_customer(self.total_wait_time),
+            ),
+            ("Maximum waiting time (s)", self.max_wait_time),
+            (
+                "Average service time (s)",
+                per_customer(self.total_service_time),
+            ),
+            (
+                "Average time in system (s)",
+                per_customer(self.total_system_time),
+            ),
+            ("Maximum time in system (s)", self.max_system_time),
+            ("Average queue length", per_time(self.queue_length_integral)),
+            ("Maximum queue length", self.max_queue_length as f64),
+            ("Server utilization (%)", utilization),
+            (
+                "Throughput (customers/hour)",
+                per_time(self.completed_customers as f64) * 3600.0,
+            ),
+        ];
+        if let Some(summary) = self.cost_summary(observed, num_windows) {
+            metrics.extend([
+                ("Revenue", summary.revenue),
+                ("Labor cost", summary.labor),
+                ("Lost revenue", summary.lost_revenue),
+                ("Wait penalty", summary.wait_penalty),
+                ("Total cost", summary.total_cost()),
+                ("Profit", summary.profit()),
+            ]);
+        }
+        metrics
+    }
+
+    /// Returns the revenue and costs of the `observed` seconds under the cost model, if set
+    pub fn cost_summary(&self, observed: f64, num_windows: usize) -> Option<CostSummary> {
+        let costs = self.cost_model.as_ref()?;
+        Some(costs.evaluate(
+            self.capacity(observed, num_windows),
+            self.completed_customers,
+            self.balked_customers + self.reneged_customers + self.rejected_customers,
+            self.excess_wait_time,
+        ))
+    }
+
     /// Prints comprehensive statistics report
-    pub fn print_report(&self, current_time: f64, total_customers: usize, num_windows: usize) {
+    pub fn print_report(
+        &self,
+        current_time: f64,
+        total_customers: usize,
+        num_windows: usize,
+        seed: Option<u64>,
+    ) {
         println!("\nSimulation Statistics:");
         println!("-----------------------------------------------");
+        if let Some(seed) = seed {
+            println!("Random seed: {}", seed);
+        }
+        // Averages cover the time after the warm-up period only
+        let observed = current_time - self.observation_start;
+        let total_customers = if self.observation_start > 0.0 {
+            println!(
+                "Warm-up period deleted: {} ({} customers in the system at its end)",
+                format_duration(self.observation_start),
+                self.warmup_in_system
+            );
+            self.arrivals
+        } else {
+            total_customers
+        };
         println!("Total customers processed: {}", total_customers);
         println!("Customers completed: {}", self.completed_customers);
+        if self.balked_customers > 0 {
+            let balk_pct = self.balked_customers as f64 / total_customers as f64 * 100.0;
+            println!(
+                "Customers balked: {} ({:.2}% of arrivals)",
+                self.balked_customers, balk_pct
+            );
+            println!("Lost sales (orders not placed): {}", self.balked_customers);
+        }
+        if self.rejected_customers > 0 {
+            let reject_pct = self.rejected_customers as f64 / total_customers as f64 * 100.0;
+            println!(
+                "Customers rejected (lane full): {} ({:.2}% of arrivals)",
+                self.rejected_customers, reject_pct
+            );
+        }
+        if self.blocked_customers > 0 {
+            println!(
+                "Customers blocked at the entrance: {} (average blocking time: {})",
+                self.blocked_customers,
+                format_duration(self.total_blocked_time / self.blocked_customers as f64)
+            );
+        }
+        if self.jockeyed_customers > 0 {
+            println!("Lane changes (jockeying): {}", self.jockeyed_customers);
+        }
+        if self.reneged_customers > 0 {
+            let joined = total_customers - self.balked_customers - self.rejected_customers;
+            let abandonment_pct = self.reneged_customers as f64 / joined as f64 * 100.0;
+            println!(
+                "Customers reneged: {} ({:.2}% abandonment rate)",
+                self.reneged_customers, abandonment_pct
+            );
+            println!(
+                "Average time to abandon: {}",
+                format_duration(self.total_time_to_abandon / self.reneged_customers as f64)
+            );
+        }
 
         if self.completed_customers > 0 {
             let avg_wait = self.total_wait_time / self.completed_customers as f64;
@@ -87,10 +690,24 @@ impl Statistics {
                 "Average service time per customer: {}",
                 format_duration(avg_service)
             );
+            println!(
+                "Average time in system per customer: {}",
+                format_duration(self.total_system_time / self.completed_customers as f64)
+            );
+            println!(
+                "Maximum time in system: {}",
+                format_duration(self.max_system_time)
+            );
+            println!("Waiting time percentiles: {}", self.wait_times.summary());
+            println!("Service time percentiles: {}", self.service_times.summary());
+            println!(
+                "Time in system percentiles: {}",
+                self.system_times.summary()
+            );
         }
 
-        if current_time > 0.0 {
-            let avg_queue_length = self.queue_length_integral / current_time;
+        if observed > 0.0 {
+            let avg_queue_length = self.queue_length_integral / observed;
             println!(
                 "Average queue length (time-weighted): {:.0} customers",
                 avg_queue_length.round()
@@ -99,31 +716,573 @@ impl Statistics {
                 "Maximum queue length: {} customers",
                 self.max_queue_length
             );
+            if let Some(capacity) = self.lane_capacity {
+                println!(
+                    "Lane full ({} cars): {:.2}% of time",
+                    capacity,
+                    self.lane_full_integral / observed * 100.0
+                );
+            }
 
-            let avg_busy_servers = self.server_busy_integral / current_time;
+            if self.open_windows.is_some() {
+                println!(
+                    "Average windows open (time-weighted): {:.2} of {} windows",
+                    self.capacity_integral / observed,
+                    num_windows
+                );
+            }
+            let avg_busy_servers = self.server_busy_integral / observed;
             println!(
                 "Average servers busy (time-weighted): {:.0} of {} windows",
                 avg_busy_servers.round(), num_windows
             );
-            let utilization_pct = (avg_busy_servers / num_windows as f64) * 100.0;
+            // Against the windows actually open when staffing follows a schedule
+            let capacity = self.capacity(observed, num_windows);
+            let utilization_pct = if capacity > 0.0 {
+                self.server_busy_integral / capacity * 100.0
+            } else {
+                0.0
+            };
             println!("Server utilization: {:.2}%", utilization_pct);
+            if self.breakdowns > 0 {
+                println!(
+                    "Window breakdowns: {} (downtime: {}, availability: {:.2}%)",
+                    self.breakdowns,
+                    format_duration(self.downtime_integral),
+                    availability(self.downtime_integral, capacity)
+                );
+            }
 
             // Calculate throughput (customers per hour)
-            let hours = current_time / 3600.0;
+            let hours = observed / 3600.0;
             if hours > 0.0 {
                 let throughput = self.completed_customers as f64 / hours;
                 println!("Throughput: {:.2} customers/hour", throughput);
             }
         }
 
+        if let Some(series) = &self.queue_length_series {
+            match mser5(&series.means()) {
+                Some(observations) => println!(
+                    "Warm-up detected (MSER-5 on the queue length): {}",
+                    format_duration(observations as f64 * series.interval())
+                ),
+                None => println!(
+                    "Warm-up detected (MSER-5 on the queue length): none, the run is too short to reach steady state"
+                ),
+            }
+        }
+
         // Show in-progress customers if any
-        let in_progress = total_customers - self.completed_customers;
+        let in_progress = total_customers + self.warmup_in_system
+            - self.completed_customers
+            - self.balked_customers
+            - self.reneged_customers
+            - self.rejected_customers;
         if in_progress > 0 {
             println!("\nNote: {} customers still in system (waiting or being served)", in_progress);
         }
+
+        self.print_histograms();
+        self.print_theory_report(observed, num_windows);
+        if let (Some(costs), Some(summary)) =
+            (&self.cost_model, self.cost_summary(observed, num_windows))
+        {
+            println!("\nCost and Revenue:");
+            println!("-----------------------------------------------");
+            summary.print(costs);
+        }
+        self.print_closing_report(current_time, num_windows);
+        self.print_segment_report(current_time, num_windows);
+        self.print_time_series(current_time, num_windows);
+        self.print_class_report(observed, num_windows);
+        self.print_window_report(observed);
+    }
+
+    /// Prints the histograms of the waiting time and the time in system, if bins are set
+    fn print_histograms(&self) {
+        if self.completed_customers == 0 {
+            return;
+        }
+        for (title, histogram) in [
+            ("Waiting Time Histogram", &self.wait_histogram),
+            ("Time in System Histogram", &self.system_histogram),
+        ] {
+            if let Some(histogram) = histogram {
+                println!("\n{}:", title);
+                println!("-----------------------------------------------");
+                histogram.print();
+            }
+        }
+    }
+
+    /// Prints the steady state of the queueing model next to the simulated values, flagging
+    /// averages whose 95% confidence interval (batch means) excludes the theoretical value
+    ///
+    /// Nothing is printed without a steady state: the overload warning before the run
+    /// already reports it.
+    fn print_theory_report(&self, observed: f64, num_windows: usize) {
+        let Some(model) = &self.queueing_model else {
+            return;
+        };
+        let Some(theory) = model.steady_state() else {
+            return;
+        };
+        println!("\nQueueing Theory ({}, steady state):", model.name());
+        println!("-----------------------------------------------");
+        if observed <= 0.0 || self.completed_customers == 0 {
+            return;
+        }
+        println!("{:<24} {:>16} {:>24}", "", "Theory", "Simulated (95% CI)");
+        let capacity = self.capacity(observed, num_windows);
+        let utilization = if capacity > 0.0 {
+            self.server_busy_integral / capacity
+        } else {
+            0.0
+        };
+        println!(
+            "{:<24} {:>15.2}% {:>23.2}%",
+            "Server utilization",
+            theory.utilization * 100.0,
+            utilization * 100.0
+        );
+        println!(
+            "{:<24} {:>16.2} {:>24.2}",
+            "Average queue length",
+            theory.avg_queue_length,
+            self.queue_length_integral / observed
+        );
+        let n = self.completed_customers as f64;
+        for (name, expected, total, batches) in [
+            (
+                "Average waiting time",
+                theory.avg_wait,
+                self.total_wait_time,
+                &self.wait_batches,
+            ),
+            (
+                "Average time in system",
+                theory.avg_system_time,
+                self.total_system_time,
+                &self.system_batches,
+            ),
+        ] {
+            let mean = total / n;
+            let (simulated, flag) = match batches.confidence_interval() {
+                Some((_, half_width)) => (
+                    format!(
+                        "{} ± {}",
+                        format_duration(mean),
+                        format_duration(half_width)
+                    ),
+                    if (expected - mean).abs() > half_width {
+                        "  (outside the confidence interval)"
+                    } else {
+                        ""
+                    },
+                ),
+                None => (format_duration(mean), ""),
+            };
+            println!(
+                "{:<24} {:>16} {:>24}{}",
+                name,
+                format_duration(expected),
+                simulated,
+                flag
+            );
+        }
+    }
+
+    /// Prints the open hours and the overtime after closing separately
+    fn print_closing_report(&self, current_time: f64, num_windows: usize) {
+        let Some(closing) = &self.closing else {
+            return;
+        };
+        let open_hours = closing.time - self.observation_start;
+        let capacity_at_close = match self.open_windows {
+            Some(_) => closing.capacity_integral,
+            None => open_hours * num_windows as f64,
+        };
+        let capacity = self.capacity(current_time - self.observation_start, num_windows);
+
+        println!("\nOpen Hours vs. Overtime:");
+        println!("-----------------------------------------------");
+        println!("Open hours (until {}):", format_duration(closing.time));
+        println!("  Customers completed: {}", closing.completed_customers);
+        if closing.completed_customers > 0 {
+            println!(
+                "  Average waiting time: {}",
+                format_duration(closing.total_wait_time / closing.completed_customers as f64)
+            );
+        }
+        if open_hours > 0.0 {
+            println!(
+                "  Average queue length (time-weighted): {:.2} customers",
+                closing.queue_length_integral / open_hours
+            );
+        }
+        if capacity_at_close > 0.0 {
+            println!(
+                "  Server utilization: {:.2}%",
+                closing.server_busy_integral / capacity_at_close * 100.0
+            );
+        }
+
+        let overtime = current_time - closing.time;
+        let completed = self.completed_customers - closing.completed_customers;
+        println!(
+            "Overtime to clear the lane: {}",
+            format_duration(overtime.max(0.0))
+        );
+        println!(
+            "  Customers in the system at closing: {}",
+            closing.in_system
+        );
+        println!("  Customers completed: {}", completed);
+        if completed > 0 {
+            println!(
+                "  Average waiting time: {}",
+                format_duration(
+                    (self.total_wait_time - closing.total_wait_time) / completed as f64
+                )
+            );
+        }
+        if overtime > 0.0 {
+            println!(
+                "  Average queue length (time-weighted): {:.2} customers",
+                (self.queue_length_integral - closing.queue_length_integral) / overtime
+            );
+        }
+        if capacity > capacity_at_close {
+            println!(
+                "  Server utilization: {:.2}%",
+                (self.server_busy_integral - closing.server_busy_integral)
+                    / (capacity - capacity_at_close)
+                    * 100.0
+            );
+        }
+    }
+
+    /// Prints statistics for each window (and its lane)
+    fn print_window_report(&self, observed: f64) {
+        if self.windows.is_empty() {
+            return;
+        }
+
+        println!("\nPer-Window Statistics:");
+        println!("-----------------------------------------------");
+        for (window, label) in self.windows.iter().zip(&self.window_labels) {
+            println!("{}:", label);
+            println!("  Customers served: {}", window.completed_customers);
+            if window.completed_customers > 0 {
+                println!(
+                    "  Average service time: {}",
+                    format_duration(window.total_service_time / window.completed_customers as f64)
+                );
+            }
+            if observed > 0.0 {
+                let open_time = window.capacity(observed, 1);
+                if window.open_windows.is_some() {
+                    println!("  Time open: {}", format_duration(open_time));
+                }
+                let utilization_pct = if open_time > 0.0 {
+                    window.server_busy_integral / open_time * 100.0
+                } else {
+                    0.0
+                };
+                println!("  Utilization: {:.2}%", utilization_pct);
+                if window.breakdowns > 0 {
+                    println!(
+                        "  Breakdowns: {} (downtime: {}, availability: {:.2}%)",
+                        window.breakdowns,
+                        format_duration(window.downtime_integral),
+                        availability(window.downtime_integral, open_time)
+                    );
+                }
+                println!(
+                    "  Throughput: {:.2} customers/hour",
+                    window.completed_customers as f64 / (observed / 3600.0)
+                );
+            }
+            if self.window_lanes {
+                let avg_wait = if window.completed_customers > 0 {
+                    window.total_wait_time / window.completed_customers as f64
+                } else {
+                    0.0
+                };
+                let avg_queue = if observed > 0.0 {
+                    window.queue_length_integral / observed
+                } else {
+                    0.0
+                };
+                println!(
+                    "  Lane: {} joined, {} jockeyed in, average wait {}, average queue {:.2}, max queue {}",
+                    window.arrivals,
+                    window.jockeyed_customers,
+                    format_duration(avg_wait),
+                    avg_queue,
+                    window.max_queue_length
+                );
+            }
+        }
+    }
+
+    /// Prints statistics for each customer class
+    fn print_class_report(&self, observed: f64, num_windows: usize) {
+        if self.classes.is_empty() {
+            return;
+        }
+
+        println!("\nPer-Class Statistics:");
+        println!("-----------------------------------------------");
+        for class in &self.classes {
+            let stats = &class.stats;
+            println!("{} (priority {}):", class.name, class.priority);
+            println!("  Arrivals: {}", stats.arrivals);
+            if stats.balked_customers > 0 {
+                println!("  Customers balked: {}", stats.balked_customers);
+            }
+            if stats.reneged_customers > 0 {
+                println!(
+                    "  Customers reneged: {} (average time to abandon: {})",
+                    stats.reneged_customers,
+                    format_duration(stats.total_time_to_abandon / stats.reneged_customers as f64)
+                );
+            }
+            if stats.rejected_customers > 0 {
+                println!("  Customers rejected: {}", stats.rejected_customers);
+            }
+            if stats.blocked_customers > 0 {
+                println!(
+                    "  Customers blocked at the entrance: {}",
+                    stats.blocked_customers
+                );
+            }
+            println!("  Customers completed: {}", stats.completed_customers);
+            if stats.completed_customers > 0 {
+                let n = stats.completed_customers as f64;
+                println!(
+                    "  Average waiting time: {}",
+                    format_duration(stats.total_wait_time / n)
+                );
+                println!(
+                    "  Maximum waiting time: {}",
+                    format_duration(stats.max_wait_time)
+                );
+                println!("  Waiting time percentiles: {}", stats.wait_times.summary());
+                println!(
+                    "  Average service time: {}",
+                    format_duration(stats.total_service_time / n)
+                );
+                println!(
+                    "  Average time in system: {}",
+                    format_duration(stats.total_system_time / n)
+                );
+            }
+            if observed > 0.0 {
+                println!(
+                    "  Average queue length (time-weighted): {:.2} customers",
+                    stats.queue_length_integral / observed
+                );
+                println!(
+                    "  Maximum queue length: {} customers",
+                    stats.max_queue_length
+                );
+                let capacity = self.capacity(observed, num_windows);
+                if capacity > 0.0 {
+                    println!(
+                        "  Share of server capacity: {:.2}%",
+                        stats.server_busy_integral / capacity * 100.0
+                    );
+                }
+                println!(
+                    "  Throughput: {:.2} customers/hour",
+                    stats.completed_customers as f64 / (observed / 3600.0)
+                );
+            }
+        }
+    }
+
+    /// Prints statistics for each tracked period
+    fn print_segment_report(&self, current_time: f64, num_windows: usize) {
+        if self.segments.is_empty() {
+            return;
+        }
+
+        println!("\nPer-Segment Statistics (customers attributed by arrival time):");
+        println!("-----------------------------------------------");
+        for segment in &self.segments {
+            println!("{}:", segment.label);
+            println!("  Arrivals: {}", segment.arrivals);
+            if segment.balked_customers > 0 {
+                println!("  Customers balked: {}", segment.balked_customers);
+            }
+            if segment.reneged_customers > 0 {
+                println!("  Customers reneged: {}", segment.reneged_customers);
+            }
+            if segment.rejected_customers > 0 {
+                println!("  Customers rejected: {}", segment.rejected_customers);
+            }
+            println!("  Customers completed: {}", segment.completed_customers);
+            if segment.completed_customers > 0 {
+                let avg_wait = segment.total_wait_time / segment.completed_customers as f64;
+                println!("  Average waiting time: {}", format_duration(avg_wait));
+                println!(
+                    "  Maximum waiting time: {}",
+                    format_duration(segment.max_wait_time)
+                );
+            }
+
+            let observed = segment.to.min(current_time) - segment.from.max(self.observation_start);
+            if observed > 0.0 {
+                println!(
+                    "  Average queue length (time-weighted): {:.2} customers",
+                    segment.queue_length_integral / observed
+                );
+                println!(
+                    "  Maximum queue length: {} customers",
+                    segment.max_queue_length
+                );
+                println!(
+                    "  Server utilization: {:.2}%",
+                    self.segment_utilization(segment, observed, num_windows)
+                );
+            } else if segment.from >= current_time {
+                println!("  (segment starts after the end of the simulation)");
+            } else {
+                println!("  (segment ends within the warm-up period)");
+            }
+        }
+    }
+
+    /// Returns the utilization of `segment` in percent over the `observed` seconds of it
+    fn segment_utilization(
+        &self,
+        segment: &SegmentStatistics,
+        observed: f64,
+        num_windows: usize,
+    ) -> f64 {
+        let capacity = match self.open_windows {
+            Some(_) => segment.capacity_integral,
+            None => observed * num_windows as f64,
+        };
+        if capacity > 0.0 {
+            segment.server_busy_integral / capacity * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the time buckets that started before `current_time`, each with the seconds
+    /// of it that were simulated
+    fn observed_buckets(&self, current_time: f64) -> Vec<(&SegmentStatistics, f64)> {
+        self.time_series
+            .iter()
+            .flat_map(TimeSeries::buckets)
+            .filter(|bucket| bucket.from < current_time)
+            .map(|bucket| (bucket, bucket.to.min(current_time) - bucket.from))
+            .collect()
+    }
+
+    /// Prints one line per time bucket, if the time series is tracked
+    fn print_time_series(&self, current_time: f64, num_windows: usize) {
+        let Some(series) = &self.time_series else {
+            return;
+        };
+        println!(
+            "\nTime Series (every {}):",
+            format_duration(series.interval())
+        );
+        println!("-----------------------------------------------");
+        println!(
+            "{:<20} {:>8} {:>9} {:>14} {:>9} {:>9} {:>11}",
+            "Period",
+            "Arrivals",
+            "Completed",
+            "Average wait",
+            "Avg queue",
+            "Max queue",
+            "Utilization"
+        );
+        for (bucket, observed) in self.observed_buckets(current_time) {
+            let avg_wait = if bucket.completed_customers > 0 {
+                format_duration(bucket.total_wait_time / bucket.completed_customers as f64)
+            } else {
+                "-".to_string()
+            };
+            println!(
+                "{:<20} {:>8} {:>9} {:>14} {:>9.2} {:>9} {:>10.2}%",
+                bucket.label,
+                bucket.arrivals,
+                bucket.completed_customers,
+                avg_wait,
+                bucket.queue_length_integral / observed,
+                bucket.max_queue_length,
+                self.segment_utilization(bucket, observed, num_windows)
+            );
+        }
+    }
+
+    /// Writes one row per time bucket to `filename`, times in seconds
+    pub fn write_time_series(
+        &self,
+        filename: &str,
+        current_time: f64,
+        num_windows: usize,
+        seed: Option<u64>,
+    ) -> io::Result<()> {
+        let mut file = create_csv(
+            filename,
+            seed,
+            "From,To,Arrivals,Balked,Reneged,Rejected,Completed,AvgWait,MaxWait,AvgQueue,MaxQueue,Utilization",
+        )?;
+        for (bucket, observed) in self.observed_buckets(current_time) {
+            let avg_wait = if bucket.completed_customers > 0 {
+                bucket.total_wait_time / bucket.completed_customers as f64
+            } else {
+                0.0
+            };
+            writeln!(
+                file,
+                "{:.2},{:.2},{},{},{},{},{},{:.2},{:.2},{:.4},{},{:.2}",
+                bucket.from,
+                bucket.from + observed,
+                bucket.arrivals,
+                bucket.balked_customers,
+                bucket.reneged_customers,
+                bucket.rejected_customers,
+                bucket.completed_customers,
+                avg_wait,
+                bucket.max_wait_time,
+                bucket.queue_length_integral / observed,
+                bucket.max_queue_length,
+                self.segment_utilization(bucket, observed, num_windows)
+            )?;
+        }
+        Ok(())
     }
 }
 
+/// Returns the percentage of the window time `capacity` not lost to repairs
+fn availability(downtime: f64, capacity: f64) -> f64 {
+    if capacity > 0.0 {
+        (1.0 - downtime / capacity) * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Returns the `p`-th percentile (nearest rank) of `sorted`, which is sorted in ascending order
+///
+/// At least `p` percent of the values are at most the result; 0 if there are no values.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
 impl Default for Statistics {
     fn default() -> Self {
         Self::new()
@@ -142,6 +1301,56 @@ mod tests {
         assert_eq!(stats.completed_customers, 0);
         assert_eq!(stats.max_wait_time, 0.0);
         assert_eq!(stats.max_queue_length, 0);
+        assert_eq!(stats.balked_customers, 0);
+    }
+
+    #[test]
+    fn test_record_balk() {
+        let mut stats = Statistics::new();
+        stats.set_segments(vec![SegmentStatistics::new("rush".into(), 10.0, 20.0)]);
+
+        stats.record_balk(5.0);
+        stats.record_balk(15.0);
+
+        assert_eq!(stats.balked_customers, 2);
+        assert_eq!(stats.segments[0].balked_customers, 1);
+    }
+
+    #[test]
+    fn test_lane_full_integral() {
+        let mut stats = Statistics::new();
+        stats.set_lane_capacity(3);
+
+        // Queue of 3 (full) from t=0 to t=10, 2 from t=10 to t=25, 5 from t=25 to t=30
+        stats.update_integrals(10.0, 3, 1);
+        stats.update_integrals(25.0, 2, 1);
+        stats.update_integrals(30.0, 5, 1);
+
+        assert_eq!(stats.lane_full_integral, 15.0);
+    }
+
+    #[test]
+    fn test_record_renege() {
+        let mut stats = Statistics::new();
+
+        stats.record_renege(0.0, 120.0);
+        stats.record_renege(10.0, 60.0);
+
+        assert_eq!(stats.reneged_customers, 2);
+        assert_eq!(stats.total_time_to_abandon, 180.0);
+    }
+
+    #[test]
+    fn test_renege_of_warmup_arrival_not_counted() {
+        let mut stats = Statistics::new();
+        stats.reset(100.0, 1);
+
+        stats.record_renege(50.0, 80.0);
+        assert_eq!(stats.reneged_customers, 0);
+        assert_eq!(stats.total_time_to_abandon, 0.0);
+
+        stats.record_renege(120.0, 30.0);
+        assert_eq!(stats.reneged_customers, 1);
     }
 
     #[test]
@@ -159,6 +1368,8 @@ mod tests {
         assert_eq!(stats.total_wait_time, 25.0);
         assert_eq!(stats.total_service_time, 45.0);
         assert_eq!(stats.max_wait_time, 15.0);
+        assert_eq!(stats.total_system_time, 70.0);
+        assert_eq!(stats.max_system_time, 40.0);
     }
 
     #[test]
@@ -229,5 +1440,163 @@ mod tests {
         // Should be (0*10 + 5*20) / 30 = 100/30 = 3.33
         assert!((avg_queue - 3.333).abs() < 0.01);
     }
+
+    #[test]
+    fn test_segment_integrals_split_at_boundaries() {
+        let mut stats = Statistics::new();
+        stats.set_segments(vec![SegmentStatistics::new("rush".into(), 10.0, 20.0)]);
+
+        // 2 in queue from t=0 to t=15, then 4 in queue from t=15 to t=30
+        stats.update_integrals(15.0, 2, 1);
+        stats.update_integrals(30.0, 4, 2);
+
+        let segment = &stats.segments[0];
+        assert_eq!(segment.queue_length_integral, 2.0 * 5.0 + 4.0 * 5.0);
+        assert_eq!(segment.server_busy_integral, 1.0 * 5.0 + 2.0 * 5.0);
+        assert_eq!(stats.queue_length_integral, 2.0 * 15.0 + 4.0 * 15.0);
+    }
+
+    #[test]
+    fn test_capacity_follows_open_windows() {
+        let mut stats = Statistics::new();
+        stats.set_segments(vec![SegmentStatistics::new("rush".into(), 10.0, 20.0)]);
+        stats.set_open_windows(2);
+
+        // 2 windows open from t=0 to t=15, then 1 window from t=15 to t=30
+        stats.update_integrals(15.0, 0, 1);
+        stats.set_open_windows(1);
+        stats.update_integrals(30.0, 0, 1);
+
+        assert_eq!(stats.capacity_integral, 2.0 * 15.0 + 1.0 * 15.0);
+        assert_eq!(stats.capacity(30.0, 2), 45.0);
+        assert_eq!(stats.segments[0].capacity_integral, 2.0 * 5.0 + 1.0 * 5.0);
+        assert_eq!(Statistics::new().capacity(30.0, 2), 60.0);
+    }
+
+    #[test]
+    fn test_downtime_integral() {
+        let mut stats = Statistics::new();
+
+        // One of two windows out of service from t=10 to t=40
+        stats.update_integrals(10.0, 0, 2);
+        stats.record_breakdown(1);
+        stats.update_integrals(40.0, 0, 1);
+        stats.set_down_windows(0);
+        stats.update_integrals(100.0, 0, 2);
+
+        assert_eq!(stats.breakdowns, 1);
+        assert_eq!(stats.downtime_integral, 30.0);
+        assert_eq!(availability(stats.downtime_integral, 200.0), 85.0);
+    }
+
+    #[test]
+    fn test_reset_deletes_warmup() {
+        let mut stats = Statistics::new();
+        stats.set_segments(vec![SegmentStatistics::new("rush".into(), 10.0, 20.0)]);
+        stats.set_lane_capacity(5);
+        stats.track_queue_length(10.0);
+        stats.record_arrival(12.0);
+        stats.record_completion(8.0, 30.0);
+        stats.update_max_queue(4);
+        stats.update_integrals(30.0, 3, 1);
+
+        stats.reset(30.0, 4);
+        stats.update_integrals(40.0, 1, 1);
+
+        assert_eq!(stats.arrivals, 0);
+        assert_eq!(stats.completed_customers, 0);
+        assert_eq!(stats.max_queue_length, 0);
+        assert_eq!(stats.queue_length_integral, 10.0);
+        assert_eq!(stats.observation_start, 30.0);
+        assert_eq!(stats.warmup_in_system, 4);
+        assert_eq!(stats.lane_capacity, Some(5));
+        assert_eq!(stats.segments[0].label, "rush");
+        assert_eq!(stats.segments[0].arrivals, 0);
+        assert_eq!(
+            stats.queue_length_series.unwrap().means(),
+            vec![3.0, 3.0, 3.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_completion_distributions() {
+        let mut stats = Statistics::new();
+        stats.set_histogram(HistogramBins {
+            width: 60.0,
+            count: 2,
+        });
+        stats.record_completion(0.0, 30.0);
+        stats.record_completion(45.0, 30.0);
+        stats.record_completion(150.0, 60.0);
+
+        assert!((stats.wait_times.quantile(50.0) - 45.0).abs() <= 0.45);
+        assert_eq!(stats.wait_times.quantile(100.0), 150.0);
+        assert_eq!(stats.service_times.quantile(0.0), 30.0);
+        assert_eq!(stats.system_times.quantile(100.0), 210.0);
+        assert_eq!(stats.wait_histogram.as_ref().unwrap().counts(), &[2, 0, 1]);
+        assert_eq!(
+            stats.system_histogram.as_ref().unwrap().counts(),
+            &[1, 1, 1]
+        );
+
+        stats.reset(300.0, 0);
+        assert_eq!(stats.wait_times.count(), 0);
+        assert_eq!(stats.wait_histogram.unwrap().counts(), &[0, 0, 0]);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut stats = Statistics::new();
+        stats.record_completion(30.0, 60.0);
+        stats.record_completion(90.0, 120.0);
+        stats.update_integrals(3600.0, 1, 1);
+
+        let metrics = stats.metrics(3600.0, 2);
+        let metric = |name: &str| metrics.iter().find(|(n, _)| *n == name).unwrap().1;
+        assert_eq!(metric("Customers completed"), 2.0);
+        assert_eq!(metric("Average waiting time (s)"), 60.0);
+        assert_eq!(metric("Maximum waiting time (s)"), 90.0);
+        assert_eq!(metric("Average time in system (s)"), 150.0);
+        assert_eq!(metric("Maximum time in system (s)"), 210.0);
+        assert_eq!(metric("Average queue length"), 1.0);
+        assert_eq!(metric("Server utilization (%)"), 50.0);
+        assert_eq!(metric("Throughput (customers/hour)"), 2.0);
+        assert!(
+            Statistics::new()
+                .metrics(0.0, 1)
+                .iter()
+                .all(|(_, value)| *value == 0.0)
+        );
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(percentile(&values, 90.0), 9.0);
+        assert_eq!(percentile(&values, 95.0), 10.0);
+        assert_eq!(percentile(&values, 50.0), 5.0);
+        assert_eq!(percentile(&values, 0.0), 1.0);
+        assert_eq!(percentile(&[], 90.0), 0.0);
+    }
+
+    #[test]
+    fn test_segment_attribution_by_arrival() {
+        let mut stats = Statistics::new();
+        stats.set_segments(vec![SegmentStatistics::new("rush".into(), 10.0, 20.0)]);
+
+        stats.record_arrival(5.0);
+        stats.record_arrival(12.0);
+        stats.record_segment_completion(12.0, 30.0);
+        stats.record_segment_completion(25.0, 50.0);
+        stats.update_segment_max_queue(15.0, 3);
+        stats.update_segment_max_queue(25.0, 9);
+
+        let segment = &stats.segments[0];
+        assert_eq!(segment.arrivals, 1);
+        assert_eq!(segment.completed_customers, 1);
+        assert_eq!(segment.total_wait_time, 30.0);
+        assert_eq!(segment.max_wait_time, 30.0);
+        assert_eq!(segment.max_queue_length, 3);
+    }
 }
 
@@ -0,0 +1,331 @@
+use crate::distribution::ServiceDistribution;
+use crate::duration::{deserialize_duration_list, format_duration};
+use crate::replication::{MetricSummary, Replications};
+use crate::simulation::Simulation;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{self, Write};
+
+/// Values of the parameters that vary between the runs of a sweep
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesignPoint {
+    pub num_windows: usize,
+    pub avg_arrival_interval: f64,
+    /// Service time distribution (None when customer classes bring their own)
+    pub service: Option<ServiceDistribution>,
+}
+
+impl DesignPoint {
+    /// Returns a description of the design point for messages
+    pub fn label(&self) -> String {
+        let mut label = format!(
+            "{} windows, arrivals every {}",
+            self.num_windows,
+            format_duration(self.avg_arrival_interval)
+        );
+        if let Some(service) = &self.service {
+            label.push_str(&format!(
+                ", {} service (mean {})",
+                service.name(),
+                format_duration(service.mean())
+            ));
+        }
+        label
+    }
+}
+
+/// Capacity study over every combination of the listed parameter values
+///
+/// Parameters without values keep the value of the rest of the configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Sweep {
+    #[serde(default)]
+    pub num_windows: Vec<usize>,
+    #[serde(default, deserialize_with = "deserialize_duration_list")]
+    pub avg_arrival_interval: Vec<f64>,
+    #[serde(default)]
+    pub service_distribution: Vec<ServiceDistribution>,
+    /// CSV file receiving one row per design point
+    pub file: String,
+}
+
+impl Sweep {
+    /// Checks that every listed value is valid
+    pub fn validate(&self) -> Result<(), String> {
+        if self.num_windows.contains(&0) {
+            return Err("sweep.num_windows must be greater than 0".into());
+        }
+        if self.avg_arrival_interval.iter().any(|&i| i <= 0.0) {
+            return Err("sweep.avg_arrival_interval must be positive".into());
+        }
+        for dist in &self.service_distribution {
+            dist.validate()
+                .map_err(|e| format!("sweep.service_distribution: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the cartesian product of the listed values, taking the values of `base` for
+    /// parameters without values
+    pub fn design_points(&self, base: &DesignPoint) -> Vec<DesignPoint> {
+        let num_windows = if self.num_windows.is_empty() {
+            vec![base.num_windows]
+        } else {
+            self.num_windows.clone()
+        };
+        let intervals = if self.avg_arrival_interval.is_empty() {
+            vec![base.avg_arrival_interval]
+        } else {
+            self.avg_arrival_interval.clone()
+        };
+        let services: Vec<Option<ServiceDistribution>> = if self.service_distribution.is_empty() {
+            vec![base.service.clone()]
+        } else {
+            self.service_distribution
+                .iter()
+                .cloned()
+                .map(Some)
+                .collect()
+        };
+
+        let mut points = Vec::new();
+        for &windows in &num_windows {
+            for &interval in &intervals {
+                for service in &services {
+                    points.push(DesignPoint {
+                        num_windows: windows,
+                        avg_arrival_interval: interval,
+                        service: service.clone(),
+                    });
+                }
+            }
+        }
+        points
+    }
+}
+
+/// Replications of every design point of a sweep
+#[derive(Debug, Clone)]
+pub struct SweepResults {
+    pub points: Vec<DesignPoint>,
+    /// Replications of each design point, in the order of `points`
+    pub results: Vec<Replications>,
+}
+
+impl SweepResults {
+    /// Runs `replications` replications of every design point
+    ///
+    /// Every design point uses the same seeds (see [`Replications::run`]), so differences
+    /// between design points are not blurred by different random streams.
+    pub async fn run(
+        points: Vec<DesignPoint>,
+        replications: usize,
+        seed: u64,
+        max_time: Option<f64>,
+        mut build: impl FnMut(&DesignPoint, u64) -> Simulation,
+    ) -> Self {
+        let mut results = Vec::with_capacity(points.len());
+        for (i, point) in points.iter().enumerate() {
+            println!(
+                "\nDesign point {}/{}: {}",
+                i + 1,
+                points.len(),
+                point.label()
+            );
+            let _ = io::stdout().flush();
+            results.push(
+                Replications::run(replications, seed, max_time, |seed| build(point, seed)).await,
+            );
+        }
+        Self { points, results }
+    }
+
+    /// Prints the key metrics of every design point
+    pub fn print_summary(&self) {
+        let replications = self.results.first().map_or(0, |r| r.runs.len());
+        println!(
+            "\nSweep Results ({} design points, {} replications each, 95% confidence intervals):",
+            self.points.len(),
+            replications
+        );
+        println!("-----------------------------------------------");
+        println!(
+            "{:>7}  {:>10}  {:<13} {:>10}  {:>28}  {:>10}  {:>11}  {:>13}",
+            "Windows",
+            "Interval",
+            "Service",
+            "Mean svc",
+            "Average wait",
+            "Avg queue",
+            "Utilization",
+            "Throughput/h"
+        );
+        for (point, replications) in self.points.iter().zip(&self.results) {
+            let summary = replications.summary();
+            let metric = |name: &str| summary.iter().find(|m| m.name == name);
+            let wait = metric("Average waiting time (s)").map_or(String::new(), |m| {
+                format!(
+                    "{} ± {}",
+                    format_duration(m.mean),
+                    format_duration(m.half_width)
+                )
+            });
+            let mean = |name: &str| metric(name).map_or(0.0, |m| m.mean);
+            println!(
+                "{:>7}  {:>10}  {:<13} {:>10}  {:>28}  {:>10.2}  {:>10.2}%  {:>13.2}",
+                point.num_windows,
+                format_duration(point.avg_arrival_interval),
+                point.service.as_ref().map_or("classes", |s| s.name()),
+                point
+                    .service
+                    .as_ref()
+                    .map_or(String::new(), |s| format_duration(s.mean())),
+                wait,
+                mean("Average queue length"),
+                mean("Server utilization (%)"),
+                mean("Throughput (customers/hour)")
+            );
+        }
+        self.print_profit_ranking();
+    }
+
+    /// Prints the design points from the most to the least profitable, if costs are set
+    fn print_profit_ranking(&self) {
+        let mut profits: Vec<(&DesignPoint, MetricSummary)> = self
+            .points
+            .iter()
+            .zip(&self.results)
+            .filter_map(|(point, replications)| {
+                let profit = replications
+                    .summary()
+                    .into_iter()
+                    .find(|m| m.name == "Profit")?;
+                Some((point, profit))
+            })
+            .collect();
+        if profits.is_empty() {
+            return;
+        }
+        profits.sort_by(|a, b| b.1.mean.total_cmp(&a.1.mean));
+        println!("\nRanked by profit (mean per run, 95% confidence interval):");
+        println!("-----------------------------------------------");
+        for (rank, (point, profit)) in profits.iter().enumerate() {
+            println!(
+                "{:>3}. {:<70} {:>12.2} ± {:.2}",
+                rank + 1,
+                point.label(),
+                profit.mean,
+                profit.half_width
+            );
+        }
+    }
+
+    /// Writes one row per design point to a CSV file: the parameters, then the mean and
+    /// the half-width of the confidence interval of every metric
+    pub fn write_csv(&self, filename: &str) -> io::Result<()> {
+        let mut file = File::create(filename)?;
+        let Some(first) = self.results.first() else {
+            return Ok(());
+        };
+        let mut header = vec![
+            "num_windows".to_string(),
+            "avg_arrival_interval".to_string(),
+            "service".to_string(),
+            "mean_service_time".to_string(),
+        ];
+        for metric in first.summary() {
+            let column = column_name(metric.name);
+            header.push(format!("{}_mean", column));
+            header.push(format!("{}_ci", column));
+        }
+        writeln!(file, "{}", header.join(","))?;
+
+        for (point, replications) in self.points.iter().zip(&self.results) {
+            let mut row = vec![
+                point.num_windows.to_string(),
+                point.avg_arrival_interval.to_string(),
+                point
+                    .service
+                    .as_ref()
+                    .map_or("classes", |s| s.name())
+                    .to_string(),
+                point
+                    .service
+                    .as_ref()
+                    .map_or(String::new(), |s| s.mean().to_string()),
+            ];
+            for metric in replications.summary() {
+                row.push(metric.mean.to_string());
+                row.push(metric.half_width.to_string());
+            }
+            writeln!(file, "{}", row.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Turns a metric name into a CSV column name, e.g. `Average waiting time (s)` into
+/// `average_waiting_time_s`
+fn column_name(metric: &str) -> String {
+    metric
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> DesignPoint {
+        DesignPoint {
+            num_windows: 2,
+            avg_arrival_interval: 40.0,
+            service: Some(ServiceDistribution::Exponential { mean: 60.0 }),
+        }
+    }
+
+    #[test]
+    fn test_design_points() {
+        let sweep = Sweep {
+            num_windows: vec![1, 2, 3],
+            avg_arrival_interval: vec![30.0, 60.0],
+            service_distribution: Vec::new(),
+            file: "sweep.csv".into(),
+        };
+        let points = sweep.design_points(&base());
+
+        assert_eq!(points.len(), 6);
+        assert_eq!(points[0].num_windows, 1);
+        assert_eq!(points[1].avg_arrival_interval, 60.0);
+        assert_eq!(points[5].num_windows, 3);
+        assert!(points.iter().all(|p| p.service == base().service));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut sweep = Sweep {
+            num_windows: vec![1, 2],
+            avg_arrival_interval: Vec::new(),
+            service_distribution: vec![ServiceDistribution::Deterministic { value: 30.0 }],
+            file: "sweep.csv".into(),
+        };
+        assert!(sweep.validate().is_ok());
+        sweep.num_windows.push(0);
+        assert!(sweep.validate().is_err());
+    }
+
+    #[test]
+    fn test_column_name() {
+        assert_eq!(
+            column_name("Average waiting time (s)"),
+            "average_waiting_time_s"
+        );
+        assert_eq!(
+            column_name("Throughput (customers/hour)"),
+            "throughput_customers_hour"
+        );
+    }
+}
@@ -0,0 +1,953 @@
+use crate::arrival::ArrivalProfile;
+use crate::clock::SimClock;
+use crate::distribution::ServiceDistribution;
+use crate::duration::{format_duration, format_duration_fixed_width};
+use crate::event::EventType;
+use crate::output::{
+    OutputMessage, create_csv, print_log_footer, print_log_header, spawn_event_printer,
+};
+use crate::seed::Stream;
+use crate::statistics::Statistics;
+use rand::Rng;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Notify, mpsc};
+
+fn default_one() -> usize {
+    1
+}
+
+/// Order in which cars held at a station's servers move on when the next buffer frees up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergePolicy {
+    /// The car that finished first goes first
+    #[default]
+    FirstFinished,
+    /// Lanes take turns (zipper merge); a lane without a waiting car is skipped
+    Alternate,
+    /// The car from the lane with the most cars still queued goes first
+    LongestLaneFirst,
+}
+
+/// A car that finished service and waits at its server for space downstream
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeCandidate {
+    pub cust_id: usize,
+    pub lane: usize,
+}
+
+impl MergePolicy {
+    /// Returns the name used in the configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::FirstFinished => "first_finished",
+            Self::Alternate => "alternate",
+            Self::LongestLaneFirst => "longest_lane_first",
+        }
+    }
+
+    /// Picks which of the waiting cars (in the order they finished) merges next
+    ///
+    /// `next_lane` is the lane whose turn it is for [`MergePolicy::Alternate`] and
+    /// `lane_queues` the number of cars queued in each lane.
+    ///
+    /// # Panics
+    /// Panics if `candidates` is empty
+    pub fn select(
+        &self,
+        candidates: &[MergeCandidate],
+        next_lane: usize,
+        lane_queues: &[usize],
+    ) -> usize {
+        assert!(!candidates.is_empty(), "No car is waiting to merge");
+        match self {
+            Self::FirstFinished => 0,
+            Self::Alternate => {
+                let lanes = lane_queues.len();
+                (0..lanes)
+                    .map(|offset| (next_lane + offset) % lanes)
+                    .find_map(|lane| candidates.iter().position(|c| c.lane == lane))
+                    .unwrap_or(0)
+            }
+            Self::LongestLaneFirst => {
+                let mut best = 0;
+                for (i, candidate) in candidates.iter().enumerate() {
+                    if lane_queues[candidate.lane] > lane_queues[candidates[best].lane] {
+                        best = i;
+                    }
+                }
+                best
+            }
+        }
+    }
+}
+
+/// One stage of a multi-stage drive-through (e.g. order point, pay window, pickup window)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Station {
+    pub name: String,
+    /// Number of parallel lanes, each with its own queue and servers
+    #[serde(default = "default_one")]
+    pub lanes: usize,
+    /// Number of parallel servers in each lane
+    #[serde(default = "default_one")]
+    pub servers: usize,
+    /// Distribution of the time a customer spends being served at the station
+    pub service: ServiceDistribution,
+    /// Number of cars that fit in each lane in front of the station (unlimited when omitted)
+    ///
+    /// For the first station this is the lane; arrivals that find every lane full are turned
+    /// away. For later stations, a car finishing upstream waits at its server (blocking it)
+    /// until there is space.
+    #[serde(default)]
+    pub buffer: Option<usize>,
+    /// Order in which cars held at this station move on to the next one
+    ///
+    /// Cars are only held when the next station's buffer is full, so with several lanes a
+    /// policy other than the default requires a buffer at the next station.
+    #[serde(default)]
+    pub merge: MergePolicy,
+}
+
+impl Station {
+    /// Checks that the station is well-formed
+    pub fn validate(&self) -> Result<(), String> {
+        if self.lanes == 0 {
+            return Err(format!(
+                "station '{}': lanes must be greater than 0",
+                self.name
+            ));
+        }
+        if self.servers == 0 {
+            return Err(format!(
+                "station '{}': servers must be greater than 0",
+                self.name
+            ));
+        }
+        if self.buffer == Some(0) {
+            return Err(format!(
+                "station '{}': buffer must be greater than 0",
+                self.name
+            ));
+        }
+        self.service
+            .validate()
+            .map_err(|e| format!("station '{}': {}", self.name, e))
+    }
+
+    /// Returns the total number of servers over all lanes
+    pub fn total_servers(&self) -> usize {
+        self.lanes * self.servers
+    }
+}
+
+/// Checks every station and that every merge policy other than the default can take
+/// effect (see [`Station::merge`])
+pub fn validate_stations(stations: &[Station]) -> Result<(), String> {
+    for (i, station) in stations.iter().enumerate() {
+        station.validate()?;
+        if station.lanes > 1
+            && station.merge != MergePolicy::default()
+            && stations.get(i + 1).is_none_or(|next| next.buffer.is_none())
+        {
+            return Err(format!(
+                "station '{}': merge needs a next station with a buffer",
+                station.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A customer passing through every station in order
+#[derive(Debug, Clone)]
+pub struct TandemCustomer {
+    pub arrival_time: f64,
+    /// Service time at each station, in station order
+    pub service_durations: Vec<f64>,
+    /// Station the customer is currently at
+    pub station: usize,
+    /// Lane of the current station the customer joined
+    pub lane: usize,
+    /// Time the customer entered the buffer of the station they are currently at
+    pub queue_entry_time: Option<f64>,
+    pub departure_time: Option<f64>,
+}
+
+/// Statistics of a single station
+///
+/// `stats` covers the buffers in front of the station (queue length, wait) and its
+/// servers (busy, service time); blocked servers are tracked separately.
+#[derive(Debug)]
+pub struct StationStatistics {
+    pub name: String,
+    pub servers: usize,
+    pub stats: Statistics,
+    /// The same statistics for each lane of the station
+    pub lanes: Vec<Statistics>,
+    /// Time-weighted number of servers holding a finished car that cannot move on
+    pub blocked_integral: f64,
+    /// Number of cars that were held at this station after finishing service
+    pub blocked_customers: usize,
+}
+
+/// Holds the state of a multi-stage simulation at any point in time
+pub struct TandemState {
+    pub stations: Vec<Station>,
+    pub customers: Vec<TandemCustomer>,
+    /// Cars waiting in front of each lane of each station
+    pub queues: Vec<Vec<VecDeque<usize>>>,
+    /// Servers currently serving a car, per lane of each station
+    pub busy: Vec<Vec<usize>>,
+    /// Servers holding a finished car because the next buffer is full, per station
+    pub blocked: Vec<usize>,
+    /// Cars held at each station's servers, in the order they finished
+    pub merging: Vec<Vec<MergeCandidate>>,
+    /// Lane whose turn it is to merge into the next station (for alternate merging)
+    pub merge_turn: Vec<usize>,
+    /// Whole-system statistics (wait = time in system not spent in service)
+    pub stats: Statistics,
+    pub station_stats: Vec<StationStatistics>,
+    pub csv_file: Option<File>,
+    pub output_tx: Option<mpsc::UnboundedSender<OutputMessage>>,
+    pub current_time: f64,
+    pub seed: Option<u64>,
+}
+
+impl TandemState {
+    /// Returns the number of cars waiting in front of a station over all its lanes
+    pub fn queue_len(&self, station: usize) -> usize {
+        self.queues[station].iter().map(VecDeque::len).sum()
+    }
+
+    /// Returns the number of busy servers of a station over all its lanes
+    pub fn busy_servers(&self, station: usize) -> usize {
+        self.busy[station].iter().sum()
+    }
+
+    /// Returns the number of customers waiting, being served or blocked anywhere
+    pub fn customers_in_system(&self) -> usize {
+        (0..self.stations.len())
+            .map(|k| self.queue_len(k) + self.busy_servers(k) + self.blocked[k])
+            .sum()
+    }
+
+    /// Updates the time-weighted integrals of the whole system, every station and every lane
+    ///
+    /// Should be called before any state change to properly track statistics
+    pub fn update_integrals(&mut self, now: f64) {
+        let total_queue = (0..self.stations.len()).map(|k| self.queue_len(k)).sum();
+        let total_busy = (0..self.stations.len()).map(|k| self.busy_servers(k)).sum();
+        self.stats.update_integrals(now, total_queue, total_busy);
+
+        for k in 0..self.stations.len() {
+            let queue_len = self.queue_len(k);
+            let busy = self.busy_servers(k);
+            let station = &mut self.station_stats[k];
+            let time_passed = now - station.stats.last_event_time;
+            if time_passed > 0.0 {
+                station.blocked_integral += time_passed * self.blocked[k] as f64;
+            }
+            station.stats.update_integrals(now, queue_len, busy);
+            for (lane, lane_stats) in station.lanes.iter_mut().enumerate() {
+                lane_stats.update_integrals(now, self.queues[k][lane].len(), self.busy[k][lane]);
+            }
+        }
+        self.current_time = now;
+    }
+
+    /// Puts a customer in the shortest lane of a station that has space
+    ///
+    /// Lanes are compared by queued cars, then busy servers. Returns the lane joined,
+    /// or None if every lane is full.
+    pub fn enter_station(&mut self, now: f64, station: usize, cust_id: usize) -> Option<usize> {
+        let capacity = self.stations[station].buffer;
+        let lane = (0..self.stations[station].lanes)
+            .filter(|&l| capacity.is_none_or(|c| self.queues[station][l].len() < c))
+            .min_by_key(|&l| (self.queues[station][l].len(), self.busy[station][l]))?;
+
+        if station > 0 {
+            // The lane after the one this car came from is next to merge
+            let from_lane = self.customers[cust_id].lane;
+            self.merge_turn[station - 1] = (from_lane + 1) % self.stations[station - 1].lanes;
+        }
+        self.queues[station][lane].push_back(cust_id);
+        let customer = &mut self.customers[cust_id];
+        customer.station = station;
+        customer.lane = lane;
+        customer.queue_entry_time = Some(now);
+        self.record_history(now, EventType::Arrival, station, cust_id);
+        Some(lane)
+    }
+
+    /// Moves the next car held at `station - 1` (chosen by its merge policy) into `station`
+    ///
+    /// Returns the lane it joined, or None if no car is waiting or there is no space.
+    pub fn admit_merging(&mut self, now: f64, station: usize) -> Option<usize> {
+        let from = station.checked_sub(1)?;
+        if self.merging[from].is_empty() {
+            return None;
+        }
+        let lane_queues: Vec<usize> = self.queues[from].iter().map(VecDeque::len).collect();
+        let index = self.stations[from].merge.select(
+            &self.merging[from],
+            self.merge_turn[from],
+            &lane_queues,
+        );
+        let lane = self.enter_station(now, station, self.merging[from][index].cust_id)?;
+        self.merging[from].remove(index);
+        self.blocked[from] -= 1;
+        Some(lane)
+    }
+
+    /// Records an event at a station, sending it to the output thread and the CSV file
+    pub fn record_history(&mut self, now: f64, event: EventType, station: usize, cust_id: usize) {
+        let queue_len = self.queue_len(station);
+        let busy_servers = self.busy_servers(station);
+
+        if let Some(ref tx) = self.output_tx {
+            let _ = tx.send(OutputMessage {
+                time: now,
+                event,
+                cust_id: Some(cust_id),
+                queue_len,
+                busy_servers,
+                num_windows: self.stations[station].total_servers(),
+                station: Some(station),
+                window: None,
+                class: None,
+            });
+        }
+
+        if let Some(ref mut file) = self.csv_file {
+            let _ = writeln!(
+                file,
+                "{:.2},{},{},{},{},{}",
+                now, self.stations[station].name, event, cust_id, queue_len, busy_servers
+            );
+            let _ = file.flush();
+        }
+
+        let total_queue = (0..self.stations.len()).map(|k| self.queue_len(k)).sum();
+        self.stats.update_max_queue(total_queue);
+        let station_stats = &mut self.station_stats[station];
+        station_stats.stats.update_max_queue(queue_len);
+        for (lane, lane_stats) in station_stats.lanes.iter_mut().enumerate() {
+            lane_stats.update_max_queue(self.queues[station][lane].len());
+        }
+    }
+
+    /// Initialize CSV file for streaming events
+    pub fn init_csv(&mut self, filename: &str) -> std::io::Result<()> {
+        self.csv_file = Some(create_csv(
+            filename,
+            self.seed,
+            "Time,Station,Event,CustomerID,QueueLength,BusyServers",
+        )?);
+        Ok(())
+    }
+
+    /// Close the CSV file
+    pub fn close_csv(&mut self) {
+        if let Some(mut file) = self.csv_file.take() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Wake-up signals of one station
+struct StationSignals {
+    /// A car entered the buffer of a lane
+    work: Vec<Notify>,
+    /// Cars held at this station's servers were let into the next station
+    released: Notify,
+    /// All upstream cars have passed; idle servers may shut down
+    upstream_done: Cell<bool>,
+    /// Servers of this station still running
+    servers_running: Cell<usize>,
+}
+
+/// What a station server should do next
+enum NextStep {
+    Serve(usize, f64, Option<usize>),
+    Wait,
+    Shutdown,
+}
+
+/// A discrete-event simulation of a multi-stage drive-through
+///
+/// Customers pass through a sequence of stations, each with one or more lanes that have
+/// their own servers and buffer. A car that finishes service while the buffer of the next
+/// station is full stays at its server, blocking it (blocking-after-service); when space
+/// frees up, the station's merge policy decides which held car goes next.
+pub struct TandemSimulation {
+    clock: Arc<SimClock>,
+    pub state: Arc<Mutex<TandemState>>,
+    /// Generator of the random customers, seeded with the simulation seed (the thread-local
+    /// generator is used when None)
+    customer_rng: Option<StdRng>,
+}
+
+impl TandemSimulation {
+    /// Creates a new multi-stage simulation
+    ///
+    /// # Panics
+    /// Panics if there are no stations or any station is invalid
+    #[must_use]
+    pub fn new(stations: Vec<Station>) -> Self {
+        assert!(!stations.is_empty(), "At least one station is required");
+        if let Err(e) = validate_stations(&stations) {
+            panic!("Invalid station: {}", e);
+        }
+
+        let station_stats = stations
+            .iter()
+            .map(|s| StationStatistics {
+                name: s.name.clone(),
+                servers: s.total_servers(),
+                stats: Statistics::new(),
+                lanes: (0..s.lanes).map(|_| Statistics::new()).collect(),
+                blocked_integral: 0.0,
+                blocked_customers: 0,
+            })
+            .collect();
+        let queues = stations
+            .iter()
+            .map(|s| vec![VecDeque::new(); s.lanes])
+            .collect();
+        let busy = stations.iter().map(|s| vec![0; s.lanes]).collect();
+        let n = stations.len();
+
+        Self {
+            clock: Arc::new(SimClock::new()),
+            state: Arc::new(Mutex::new(TandemState {
+                stations,
+                customers: Vec::new(),
+                queues,
+                busy,
+                blocked: vec![0; n],
+                merging: vec![Vec::new(); n],
+                merge_turn: vec![0; n],
+                stats: Statistics::new(),
+                station_stats,
+                csv_file: None,
+                output_tx: None,
+                current_time: 0.0,
+                seed: None,
+            })),
+            customer_rng: None,
+        }
+    }
+
+    /// Creates a new multi-stage simulation whose random customer generation is reproducible
+    ///
+    /// # Panics
+    /// Panics if there are no stations or any station is invalid
+    #[must_use]
+    pub fn with_seed(stations: Vec<Station>, seed: u64) -> Self {
+        let mut sim = Self::new(stations);
+        sim.state.lock().unwrap().seed = Some(seed);
+        sim.customer_rng = Some(Stream::Customers.rng(seed));
+        sim
+    }
+
+    /// Adds a customer with the given service time at each station
+    ///
+    /// # Panics
+    /// Panics if `arrival_time` is negative, the number of service durations does not
+    /// match the number of stations, or any service duration is not positive
+    pub fn add_customer(&mut self, arrival_time: f64, service_durations: Vec<f64>) {
+        assert!(arrival_time >= 0.0, "Arrival time must be non-negative");
+        let mut state = self.state.lock().unwrap();
+        assert_eq!(
+            service_durations.len(),
+            state.stations.len(),
+            "One service duration per station is required"
+        );
+        assert!(
+            service_durations.iter().all(|&d| d > 0.0),
+            "Service durations must be positive"
+        );
+        state.customers.push(TandemCustomer {
+            arrival_time,
+            service_durations,
+            station: 0,
+            lane: 0,
+            queue_entry_time: None,
+            departure_time: None,
+        });
+    }
+
+    /// Generates random customers, drawing service times from each station's distribution
+    ///
+    /// Uses a generator seeded with the simulation seed if one was set, otherwise the
+    /// thread-local generator. Further calls continue the stream of the seeded generator.
+    pub fn generate_customers(&mut self, max_time: f64, arrivals: &ArrivalProfile) {
+        match self.customer_rng.take() {
+            Some(mut rng) => {
+                self.generate_customers_with_rng(&mut rng, max_time, arrivals);
+                self.customer_rng = Some(rng);
+            }
+            None => self.generate_customers_with_rng(&mut rand::rng(), max_time, arrivals),
+        }
+    }
+
+    /// Generates random customers drawing from `rng`
+    ///
+    /// # Panics
+    /// Panics if `max_time` is not positive or the arrival profile is invalid
+    pub fn generate_customers_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        max_time: f64,
+        arrivals: &ArrivalProfile,
+    ) {
+        assert!(max_time > 0.0, "Max time must be positive");
+        if let Err(e) = arrivals.validate() {
+            panic!("Invalid arrival profile: {}", e);
+        }
+
+        let services: Vec<ServiceDistribution> = self
+            .state
+            .lock()
+            .unwrap()
+            .stations
+            .iter()
+            .map(|s| s.service.clone())
+            .collect();
+
+        for arrival_time in arrivals.generate_arrivals(rng, max_time) {
+            let durations = services.iter().map(|d| d.sample(rng)).collect();
+            self.add_customer(arrival_time, durations);
+        }
+    }
+
+    /// Runs the simulation
+    ///
+    /// # Arguments
+    /// * `max_time` - Optional maximum simulation time. If None, runs until all customers are served.
+    /// * `csv_filename` - Optional CSV filename for streaming event history
+    pub async fn run(&mut self, max_time: Option<f64>, csv_filename: Option<&str>) {
+        self.state
+            .lock()
+            .unwrap()
+            .customers
+            .sort_by(|a, b| a.arrival_time.total_cmp(&b.arrival_time));
+
+        if let Some(filename) = csv_filename
+            && let Err(e) = self.state.lock().unwrap().init_csv(filename)
+        {
+            eprintln!("Warning: Failed to create CSV file {}: {}", filename, e);
+        }
+
+        let names: Vec<String> = self
+            .state
+            .lock()
+            .unwrap()
+            .stations
+            .iter()
+            .map(|s| s.name.clone())
+            .collect();
+        let (output_tx, output_rx) = mpsc::unbounded_channel::<OutputMessage>();
+        self.state.lock().unwrap().output_tx = Some(output_tx);
+
+        let output_handle = spawn_event_printer(output_rx, move |msg| {
+            format!(
+                "{} {:<12} {:<15} {:<10} {:<10} {}/{}",
+                format_duration_fixed_width(msg.time),
+                msg.station.map_or("", |k| names[k].as_str()),
+                format!("{:?}", msg.event),
+                msg.cust_id.map_or(String::new(), |id| id.to_string()),
+                msg.queue_len,
+                msg.busy_servers,
+                msg.num_windows
+            )
+        });
+
+        print_log_header(
+            "Starting multi-stage simulation (Coroutine-based)...",
+            &format!(
+                "{:>30} {:<12} {:<15} {:<10} {:<10} BusyServers",
+                "Time", "Station", "Event", "CustID", "Queue"
+            ),
+        );
+
+        let stations = self.state.lock().unwrap().stations.clone();
+        let signals: Rc<Vec<StationSignals>> = Rc::new(
+            stations
+                .iter()
+                .map(|s| StationSignals {
+                    work: (0..s.lanes).map(|_| Notify::new()).collect(),
+                    released: Notify::new(),
+                    upstream_done: Cell::new(false),
+                    servers_running: Cell::new(s.total_servers()),
+                })
+                .collect(),
+        );
+
+        let local = tokio::task::LocalSet::new();
+
+        for (k, station) in stations.iter().enumerate() {
+            for lane in 0..station.lanes {
+                for _ in 0..station.servers {
+                    local.spawn_local(station_server(
+                        k,
+                        lane,
+                        self.state.clone(),
+                        self.clock.clone(),
+                        signals.clone(),
+                    ));
+                }
+            }
+        }
+
+        let arrival_state = self.state.clone();
+        let arrival_clock = self.clock.clone();
+        let arrival_signals = signals.clone();
+        local.spawn_local(async move {
+            let customers_len = arrival_state.lock().unwrap().customers.len();
+            for i in 0..customers_len {
+                let arrival_time = arrival_state.lock().unwrap().customers[i].arrival_time;
+                if max_time.is_some_and(|limit| arrival_time > limit) {
+                    break;
+                }
+                arrival_clock.sleep_until(arrival_time).await;
+
+                let mut s = arrival_state.lock().unwrap();
+                s.update_integrals(arrival_time);
+                match s.enter_station(arrival_time, 0, i) {
+                    Some(lane) => {
+                        drop(s);
+                        arrival_signals[0].work[lane].notify_one();
+                    }
+                    None => {
+                        s.stats.record_reject(arrival_time);
+                        s.record_history(arrival_time, EventType::Reject, 0, i);
+                    }
+                }
+            }
+
+            arrival_signals[0].upstream_done.set(true);
+            for work in &arrival_signals[0].work {
+                work.notify_waiters();
+            }
+        });
+
+        local
+            .run_until(self.clock.run(max_time, || {
+                self.state.lock().unwrap().customers_in_system()
+            }))
+            .await;
+
+        {
+            let mut s = self.state.lock().unwrap();
+            let final_time = max_time.unwrap_or_else(|| self.clock.now());
+            if s.current_time < final_time {
+                s.update_integrals(final_time);
+            }
+            s.close_csv();
+            s.output_tx = None;
+        }
+
+        let _ = output_handle.await;
+
+        print_log_footer(self.state.lock().unwrap().current_time);
+    }
+
+    /// Prints whole-system statistics followed by a breakdown per station
+    pub fn print_statistics(&self) {
+        let state = self.state.lock().unwrap();
+        let total_servers = state.stations.iter().map(Station::total_servers).sum();
+        state.stats.print_report(
+            state.current_time,
+            state.customers.len(),
+            total_servers,
+            state.seed,
+        );
+
+        println!("\nPer-Station Statistics:");
+        println!("-----------------------------------------------");
+        for (station, station_stats) in state.stations.iter().zip(&state.station_stats) {
+            print_station_report(station, station_stats, state.current_time);
+        }
+    }
+}
+
+/// Prints the statistics of a single station and, if it has several lanes, of each lane
+fn print_station_report(station: &Station, station_stats: &StationStatistics, current_time: f64) {
+    let stats = &station_stats.stats;
+    println!(
+        "{} ({} servers):",
+        station_stats.name, station_stats.servers
+    );
+    println!("  Customers served: {}", stats.completed_customers);
+    if stats.completed_customers > 0 {
+        let n = stats.completed_customers as f64;
+        println!(
+            "  Average waiting time in front of station: {}",
+            format_duration(stats.total_wait_time / n)
+        );
+        println!(
+            "  Maximum waiting time: {}",
+            format_duration(stats.max_wait_time)
+        );
+        println!(
+            "  Average service time: {}",
+            format_duration(stats.total_service_time / n)
+        );
+    }
+    if current_time > 0.0 {
+        println!(
+            "  Average queue length (time-weighted): {:.2} customers",
+            stats.queue_length_integral / current_time
+        );
+        println!(
+            "  Maximum queue length: {} customers",
+            stats.max_queue_length
+        );
+        let servers = station_stats.servers as f64;
+        println!(
+            "  Server utilization: {:.2}%",
+            stats.server_busy_integral / current_time / servers * 100.0
+        );
+        if station_stats.blocked_customers > 0 {
+            println!(
+                "  Servers blocked (next buffer full): {:.2}% of capacity, {} cars held",
+                station_stats.blocked_integral / current_time / servers * 100.0,
+                station_stats.blocked_customers
+            );
+        }
+    }
+
+    if station.lanes > 1 {
+        println!("  Merge policy: {}", station.merge.name());
+        for (lane, lane_stats) in station_stats.lanes.iter().enumerate() {
+            let avg_wait = if lane_stats.completed_customers > 0 {
+                lane_stats.total_wait_time / lane_stats.completed_customers as f64
+            } else {
+                0.0
+            };
+            let (avg_queue, utilization) = if current_time > 0.0 {
+                (
+                    lane_stats.queue_length_integral / current_time,
+                    lane_stats.server_busy_integral / current_time / station.servers as f64 * 100.0,
+                )
+            } else {
+                (0.0, 0.0)
+            };
+            println!(
+                "  Lane {}: {} served, average wait {}, average queue {:.2}, max queue {}, utilization {:.2}%",
+                lane + 1,
+                lane_stats.completed_customers,
+                format_duration(avg_wait),
+                avg_queue,
+                lane_stats.max_queue_length,
+                utilization
+            );
+        }
+    }
+}
+
+/// Server loop of one lane of a station: takes cars from the lane's buffer, serves them
+/// and hands them over to the next station (waiting while its buffers are full)
+async fn station_server(
+    k: usize,
+    lane: usize,
+    state: Arc<Mutex<TandemState>>,
+    clock: Arc<SimClock>,
+    signals: Rc<Vec<StationSignals>>,
+) {
+    let last_station = k + 1 == signals.len();
+
+    loop {
+        let step = {
+            let mut s = state.lock().unwrap();
+            if s.queues[k][lane].is_empty() {
+                if signals[k].upstream_done.get() {
+                    NextStep::Shutdown
+                } else {
+                    NextStep::Wait
+                }
+            } else {
+                let now = clock.now();
+                s.update_integrals(now);
+                let cust_id = s.queues[k][lane].pop_front().unwrap();
+                s.busy[k][lane] += 1;
+
+                let customer = &s.customers[cust_id];
+                let wait = now - customer.queue_entry_time.unwrap_or(now);
+                let duration = customer.service_durations[k];
+                s.station_stats[k].stats.record_completion(wait, duration);
+                s.station_stats[k].lanes[lane].record_completion(wait, duration);
+                s.record_history(now, EventType::ServiceStart, k, cust_id);
+
+                // A buffer slot in front of this lane was freed for a held upstream car
+                let admitted = s.admit_merging(now, k);
+                NextStep::Serve(cust_id, duration, admitted)
+            }
+        };
+
+        let (cust_id, duration) = match step {
+            NextStep::Serve(cust_id, duration, admitted) => {
+                if let Some(admitted_lane) = admitted {
+                    signals[k - 1].released.notify_waiters();
+                    signals[k].work[admitted_lane].notify_one();
+                }
+                (cust_id, duration)
+            }
+            NextStep::Wait => {
+                signals[k].work[lane].notified().await;
+                continue;
+            }
+            NextStep::Shutdown => break,
+        };
+
+        clock.sleep(duration).await;
+
+        let entered = {
+            let mut s = state.lock().unwrap();
+            let now = clock.now();
+            s.update_integrals(now);
+            s.busy[k][lane] -= 1;
+            s.record_history(now, EventType::ServiceEnd, k, cust_id);
+
+            if last_station {
+                let customer = &mut s.customers[cust_id];
+                customer.departure_time = Some(now);
+                let service: f64 = customer.service_durations.iter().sum();
+                let wait = now - customer.arrival_time - service;
+                s.stats.record_completion(wait.max(0.0), service);
+                continue;
+            }
+
+            // Cars already held here go first; they are released by the merge policy
+            let entered = if s.merging[k].is_empty() {
+                s.enter_station(now, k + 1, cust_id)
+            } else {
+                None
+            };
+            if entered.is_none() {
+                s.blocked[k] += 1;
+                s.station_stats[k].blocked_customers += 1;
+                s.merging[k].push(MergeCandidate { cust_id, lane });
+                s.record_history(now, EventType::Blocked, k, cust_id);
+            }
+            entered
+        };
+
+        match entered {
+            Some(next_lane) => signals[k + 1].work[next_lane].notify_one(),
+            // Blocking-after-service: hold the server until the car is let through
+            None => {
+                while state.lock().unwrap().customers[cust_id].station == k {
+                    signals[k].released.notified().await;
+                }
+            }
+        }
+    }
+
+    // The last server of a station to shut down releases the next station
+    let running = signals[k].servers_running.get() - 1;
+    signals[k].servers_running.set(running);
+    if running == 0 && !last_station {
+        signals[k + 1].upstream_done.set(true);
+        for work in &signals[k + 1].work {
+            work.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(lanes: &[usize]) -> Vec<MergeCandidate> {
+        lanes
+            .iter()
+            .enumerate()
+            .map(|(cust_id, &lane)| MergeCandidate { cust_id, lane })
+            .collect()
+    }
+
+    #[test]
+    fn test_first_finished_merges_in_order() {
+        let waiting = candidates(&[2, 0, 1]);
+        assert_eq!(
+            MergePolicy::FirstFinished.select(&waiting, 1, &[0, 5, 0]),
+            0
+        );
+    }
+
+    #[test]
+    fn test_alternate_takes_lane_whose_turn_it_is() {
+        let waiting = candidates(&[2, 0, 1]);
+        assert_eq!(MergePolicy::Alternate.select(&waiting, 0, &[0, 0, 0]), 1);
+        assert_eq!(MergePolicy::Alternate.select(&waiting, 1, &[0, 0, 0]), 2);
+
+        // Lane 1 has no car waiting, so its turn passes to lane 2
+        let waiting = candidates(&[0, 2]);
+        assert_eq!(MergePolicy::Alternate.select(&waiting, 1, &[0, 0, 0]), 1);
+    }
+
+    #[test]
+    fn test_longest_lane_first_breaks_ties_by_finish_order() {
+        let waiting = candidates(&[0, 1, 2]);
+        assert_eq!(
+            MergePolicy::LongestLaneFirst.select(&waiting, 0, &[1, 4, 2]),
+            1
+        );
+        assert_eq!(
+            MergePolicy::LongestLaneFirst.select(&waiting, 0, &[3, 1, 3]),
+            0
+        );
+    }
+
+    #[test]
+    fn test_merge_policy_needs_buffer_downstream() {
+        let station =
+            |name: &str, lanes: usize, buffer: Option<usize>, merge: MergePolicy| Station {
+                name: name.into(),
+                lanes,
+                servers: 1,
+                service: ServiceDistribution::Deterministic { value: 30.0 },
+                buffer,
+                merge,
+            };
+        let order = |merge| station("order", 2, None, merge);
+
+        // The default policy works with or without a buffer downstream
+        for pickup_buffer in [None, Some(2)] {
+            let stations = [
+                order(MergePolicy::FirstFinished),
+                station("pickup", 1, pickup_buffer, MergePolicy::default()),
+            ];
+            assert!(validate_stations(&stations).is_ok());
+        }
+        // Without a buffer downstream, no car is ever held for the policy to order
+        let stations = [
+            order(MergePolicy::Alternate),
+            station("pickup", 1, None, MergePolicy::default()),
+        ];
+        assert!(validate_stations(&stations).is_err());
+        assert!(validate_stations(&[order(MergePolicy::LongestLaneFirst)]).is_err());
+        let stations = [
+            order(MergePolicy::Alternate),
+            station("pickup", 1, Some(2), MergePolicy::default()),
+        ];
+        assert!(validate_stations(&stations).is_ok());
+
+        // A single lane has nothing to merge
+        let stations = [
+            station("order", 1, None, MergePolicy::Alternate),
+            station("pickup", 1, None, MergePolicy::default()),
+        ];
+        assert!(validate_stations(&stations).is_ok());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::duration::{deserialize_duration, format_duration};
+use crate::statistics::SegmentStatistics;
+use serde::Deserialize;
+
+/// Width of the time buckets of the time series and the CSV file it is written to
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TimeBuckets {
+    /// Width of each bucket (e.g. "15m")
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: f64,
+    /// CSV file with one row per bucket (only the report when omitted)
+    #[serde(default)]
+    pub file: Option<String>,
+}
+
+impl TimeBuckets {
+    /// Checks that the interval is positive
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval <= 0.0 {
+            return Err("time_series.interval must be positive".into());
+        }
+        Ok(())
+    }
+}
+
+/// Statistics of consecutive time buckets of equal width, to follow how the system evolves
+/// through the day
+///
+/// Customers are counted in the bucket of the event: arrivals, balks and rejections when
+/// they arrive, reneges when they leave and completions (with their wait) when their service
+/// ends. Time-weighted integrals are split over the buckets they span.
+#[derive(Debug, Clone)]
+pub struct TimeSeries {
+    interval: f64,
+    /// Buckets up to the last one an event fell into
+    buckets: Vec<SegmentStatistics>,
+}
+
+impl TimeSeries {
+    /// Creates an empty series of buckets of width `interval`
+    ///
+    /// # Panics
+    /// Panics if `interval` is not positive
+    #[must_use]
+    pub fn new(interval: f64) -> Self {
+        assert!(interval > 0.0, "Interval must be positive");
+        Self {
+            interval,
+            buckets: Vec::new(),
+        }
+    }
+
+    /// Returns the width of the buckets
+    pub fn interval(&self) -> f64 {
+        self.interval
+    }
+
+    /// Returns the buckets from time 0 to the last one an event fell into
+    pub fn buckets(&self) -> &[SegmentStatistics] {
+        &self.buckets
+    }
+
+    /// Returns the bucket containing `time`, adding the buckets up to it
+    fn bucket(&mut self, time: f64) -> &mut SegmentStatistics {
+        let index = (time.max(0.0) / self.interval) as usize;
+        self.bucket_at(index)
+    }
+
+    /// Returns the bucket at `index`, adding the buckets up to it
+    fn bucket_at(&mut self, index: usize) -> &mut SegmentStatistics {
+        while self.buckets.len() <= index {
+            let from = self.buckets.len() as f64 * self.interval;
+            let to = (self.buckets.len() + 1) as f64 * self.interval;
+            let label = format!("{} - {}", format_duration(from), format_duration(to));
+            self.buckets.push(SegmentStatistics::new(label, from, to));
+        }
+        &mut self.buckets[index]
+    }
+
+    /// Records `queue_len` waiting customers, `busy_servers` busy and `open_windows` open
+    /// windows from `start` to `end`
+    pub fn record_integrals(
+        &mut self,
+        start: f64,
+        end: f64,
+        queue_len: usize,
+        busy_servers: usize,
+        open_windows: usize,
+    ) {
+        // The index advances on every step: recomputing it from `time` can map a bucket's
+        // end back into the same bucket when the interval is not exactly representable
+        let mut index = (start.max(0.0) / self.interval) as usize;
+        let mut time = start;
+        while time < end {
+            let bucket = self.bucket_at(index);
+            let boundary = bucket.to.min(end);
+            if boundary > time {
+                bucket.queue_length_integral += (boundary - time) * queue_len as f64;
+                bucket.server_busy_integral += (boundary - time) * busy_servers as f64;
+                bucket.capacity_integral += (boundary - time) * open_windows as f64;
+                bucket.max_queue_length = bucket.max_queue_length.max(queue_len);
+                time = boundary;
+            }
+            index += 1;
+        }
+    }
+
+    /// Records a customer arriving at `time`
+    pub fn record_arrival(&mut self, time: f64) {
+        self.bucket(time).arrivals += 1;
+    }
+
+    /// Records a customer balking at `time`
+    pub fn record_balk(&mut self, time: f64) {
+        self.bucket(time).balked_customers += 1;
+    }
+
+    /// Records a customer turned away at `time`
+    pub fn record_reject(&mut self, time: f64) {
+        self.bucket(time).rejected_customers += 1;
+    }
+
+    /// Records a customer leaving the queue at `time`
+    pub fn record_renege(&mut self, time: f64) {
+        self.bucket(time).reneged_customers += 1;
+    }
+
+    /// Records a customer whose service ended at `time` after waiting `wait_time`
+    pub fn record_completion(&mut self, time: f64, wait_time: f64) {
+        let bucket = self.bucket(time);
+        bucket.completed_customers += 1;
+        bucket.total_wait_time += wait_time;
+        bucket.max_wait_time = bucket.max_wait_time.max(wait_time);
+    }
+
+    /// Updates the maximum queue length of the bucket containing `time`
+    pub fn update_max_queue(&mut self, time: f64, queue_len: usize) {
+        let bucket = self.bucket(time);
+        bucket.max_queue_length = bucket.max_queue_length.max(queue_len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integrals_split_over_buckets() {
+        let mut series = TimeSeries::new(900.0);
+        series.record_integrals(600.0, 1200.0, 2, 1, 2);
+        series.record_arrival(100.0);
+        series.record_completion(2000.0, 30.0);
+
+        let buckets = series.buckets();
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[1].label, "15m - 30m");
+        assert_eq!(buckets[0].queue_length_integral, 600.0);
+        assert_eq!(buckets[1].queue_length_integral, 600.0);
+        assert_eq!(buckets[1].server_busy_integral, 300.0);
+        assert_eq!(buckets[1].max_queue_length, 2);
+        assert_eq!(buckets[0].arrivals, 1);
+        assert_eq!(buckets[2].completed_customers, 1);
+        assert_eq!(buckets[2].max_wait_time, 30.0);
+        assert!(
+            TimeBuckets {
+                interval: 0.0,
+                file: None
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_integrals_with_fractional_interval() {
+        let mut series = TimeSeries::new(0.7);
+        series.record_integrals(0.0, 5.0, 1, 1, 1);
+
+        let buckets = series.buckets();
+        assert_eq!(buckets.len(), 8);
+        let total: f64 = buckets.iter().map(|b| b.queue_length_integral).sum();
+        assert!((total - 5.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,134 @@
+/// Average queue length over consecutive intervals of equal width, the time series the
+/// warm-up period is detected on
+#[derive(Debug, Clone)]
+pub struct QueueLengthSeries {
+    interval: f64,
+    /// Queue length integral of each interval (trailing empty intervals are omitted)
+    integrals: Vec<f64>,
+    end: f64,
+}
+
+impl QueueLengthSeries {
+    /// Creates an empty series of intervals of width `interval`
+    ///
+    /// # Panics
+    /// Panics if `interval` is not positive
+    #[must_use]
+    pub fn new(interval: f64) -> Self {
+        assert!(interval > 0.0, "Interval must be positive");
+        Self {
+            interval,
+            integrals: Vec::new(),
+            end: 0.0,
+        }
+    }
+
+    /// Returns the width of the intervals
+    pub fn interval(&self) -> f64 {
+        self.interval
+    }
+
+    /// Records `queue_len` customers waiting from `start` to `end`
+    pub fn record(&mut self, start: f64, end: f64, queue_len: usize) {
+        if queue_len > 0 {
+            let mut index = (start / self.interval) as usize;
+            let mut time = start;
+            while time < end {
+                let boundary = ((index + 1) as f64 * self.interval).min(end);
+                if boundary > time {
+                    if self.integrals.len() <= index {
+                        self.integrals.resize(index + 1, 0.0);
+                    }
+                    self.integrals[index] += (boundary - time) * queue_len as f64;
+                    time = boundary;
+                }
+                index += 1;
+            }
+        }
+        self.end = self.end.max(end);
+    }
+
+    /// Returns the average queue length of every interval recorded completely
+    pub fn means(&self) -> Vec<f64> {
+        let complete = (self.end / self.interval) as usize;
+        (0..complete)
+            .map(|i| self.integrals.get(i).copied().unwrap_or(0.0) / self.interval)
+            .collect()
+    }
+}
+
+/// Number of observations averaged into one batch by MSER-5
+const MSER_BATCH_SIZE: usize = 5;
+
+/// Chooses the warm-up period with MSER-5 (marginal standard error rule)
+///
+/// The observations are averaged in batches of 5; the truncation point is the number of
+/// leading batches, at most half of them, whose deletion minimizes the squared standard
+/// error of the mean of the remaining batches. Returns the number of observations to
+/// delete, or None if the minimum lies at half of the run, i.e. the run is too short to
+/// reach steady state.
+pub fn mser5(observations: &[f64]) -> Option<usize> {
+    let batches: Vec<f64> = observations
+        .chunks_exact(MSER_BATCH_SIZE)
+        .map(|batch| batch.iter().sum::<f64>() / MSER_BATCH_SIZE as f64)
+        .collect();
+    let n = batches.len();
+    if n < 2 {
+        return None;
+    }
+
+    let mut best = (0, f64::INFINITY);
+    for d in 0..=n / 2 {
+        let rest = &batches[d..];
+        let count = rest.len() as f64;
+        let mean = rest.iter().sum::<f64>() / count;
+        let squares: f64 = rest.iter().map(|y| (y - mean).powi(2)).sum();
+        let statistic = squares / (count * count);
+        if statistic < best.1 {
+            best = (d, statistic);
+        }
+    }
+    (best.0 < n / 2).then_some(best.0 * MSER_BATCH_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_series_splits_at_interval_boundaries() {
+        let mut series = QueueLengthSeries::new(10.0);
+        series.record(0.0, 5.0, 0);
+        series.record(5.0, 25.0, 2);
+        series.record(25.0, 38.0, 1);
+
+        // The last interval (30-40) is incomplete
+        assert_eq!(series.means(), vec![1.0, 2.0, 1.5]);
+    }
+
+    #[test]
+    fn test_mser5_truncates_initial_transient() {
+        // Queue builds up from an empty lane during the first 20 observations
+        let observations: Vec<f64> = (0..200)
+            .map(|i| {
+                if i < 20 {
+                    i as f64 / 4.0
+                } else {
+                    5.0 + (i % 5) as f64
+                }
+            })
+            .collect();
+
+        assert_eq!(mser5(&observations), Some(20));
+    }
+
+    #[test]
+    fn test_mser5_without_steady_state() {
+        let trend: Vec<f64> = (0..100).map(|i| i as f64).collect();
+        let steady: Vec<f64> = (0..100).map(|i| (i % 5) as f64).collect();
+
+        assert_eq!(mser5(&trend), None);
+        assert_eq!(mser5(&steady), Some(0));
+        assert_eq!(mser5(&[1.0; 9]), None);
+    }
+}
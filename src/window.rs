@@ -0,0 +1,140 @@
+use crate::breakdown::Breakdowns;
+use crate::class::CustomerClass;
+use serde::Deserialize;
+
+fn default_speed() -> f64 {
+    1.0
+}
+
+/// A service window with its own pace and, optionally, a restricted set of customer classes
+/// (e.g. a slower trainee window or an express window for small orders)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Window {
+    pub name: String,
+    /// Pace relative to a normal window: service times are divided by it, so 0.7 takes
+    /// about 43% longer and 1.25 is 20% faster
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+    /// Names of the customer classes the window serves (all classes when empty)
+    #[serde(default)]
+    pub classes: Vec<String>,
+    /// Failure process of this window, replacing the one shared by all windows
+    #[serde(default)]
+    pub breakdowns: Option<Breakdowns>,
+}
+
+impl Window {
+    /// Creates a window with normal speed serving every class
+    #[must_use]
+    #[allow(dead_code)] // Library API, used by the integration tests
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            speed: default_speed(),
+            classes: Vec::new(),
+            breakdowns: None,
+        }
+    }
+
+    /// Returns true if the window serves customers of the given class
+    /// (None for customers without a class)
+    pub fn serves(&self, class: Option<&CustomerClass>) -> bool {
+        self.classes.is_empty() || class.is_some_and(|c| self.classes.contains(&c.name))
+    }
+
+    /// Checks that the window parameters are consistent
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("window: name must not be empty".into());
+        }
+        if !(self.speed.is_finite() && self.speed > 0.0) {
+            return Err(format!("window '{}': speed must be positive", self.name));
+        }
+        if let Some(breakdowns) = &self.breakdowns {
+            breakdowns
+                .validate()
+                .map_err(|e| format!("window '{}': {}", self.name, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks every window, that window names are unique, that the windows only refer to
+/// existing classes and that every class is served by at least one window
+pub fn validate_windows(windows: &[Window], classes: &[CustomerClass]) -> Result<(), String> {
+    for (i, window) in windows.iter().enumerate() {
+        window.validate()?;
+        if windows[..i].iter().any(|w| w.name == window.name) {
+            return Err(format!("window '{}' is defined twice", window.name));
+        }
+        if let Some(name) = window
+            .classes
+            .iter()
+            .find(|name| !classes.iter().any(|c| &c.name == *name))
+        {
+            return Err(format!(
+                "window '{}': unknown customer class '{}'",
+                window.name, name
+            ));
+        }
+    }
+    if classes.is_empty() {
+        if !windows.is_empty() && windows.iter().all(|w| !w.classes.is_empty()) {
+            return Err("windows: at least one window must serve every customer".into());
+        }
+    } else if let Some(class) = classes
+        .iter()
+        .find(|c| !windows.is_empty() && !windows.iter().any(|w| w.serves(Some(c))))
+    {
+        return Err(format!("windows: no window serves class '{}'", class.name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(name: &str) -> CustomerClass {
+        CustomerClass {
+            name: name.into(),
+            share: 1.0,
+            service: None,
+            priority: 0,
+        }
+    }
+
+    fn window(name: &str, classes: &[&str]) -> Window {
+        Window {
+            classes: classes.iter().map(|c| c.to_string()).collect(),
+            ..Window::new(name)
+        }
+    }
+
+    #[test]
+    fn test_serves() {
+        let express = window("express", &["small"]);
+        assert!(express.serves(Some(&class("small"))));
+        assert!(!express.serves(Some(&class("large"))));
+        assert!(!express.serves(None));
+        assert!(Window::new("main").serves(None));
+    }
+
+    #[test]
+    fn test_validate_windows() {
+        let classes = [class("small"), class("large")];
+        let main = Window::new("main");
+        let express = window("express", &["small"]);
+
+        assert!(validate_windows(&[main.clone(), express.clone()], &classes).is_ok());
+        assert!(validate_windows(std::slice::from_ref(&express), &classes).is_err());
+        assert!(validate_windows(&[main.clone(), main.clone()], &classes).is_err());
+        assert!(validate_windows(&[main.clone(), window("x", &["medium"])], &classes).is_err());
+        assert!(validate_windows(&[express], &[]).is_err());
+        let slow = Window {
+            speed: 0.0,
+            ..Window::new("slow")
+        };
+        assert!(validate_windows(&[slow], &[]).is_err());
+    }
+}